@@ -0,0 +1,190 @@
+//! HTTP API for operators to inspect and manage rooms, under `/admin`.
+//!
+//! Requests must carry the admin token in an `Authorization: Bearer <token>`
+//! header.  Without a configured token, the API is disabled.
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+use warp::{Filter, Rejection, Reply};
+use warp::http::StatusCode;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::assets;
+use crate::game::Room;
+
+pub use engine::admin::*;
+
+/// HTTP status of a failed action
+fn status(error: &AdminError) -> StatusCode {
+	match error {
+		AdminError::NoSuchPlayer => StatusCode::NOT_FOUND,
+		AdminError::NotEnoughPlayers | AdminError::NoGame => StatusCode::CONFLICT,
+		AdminError::EmptyDeck => StatusCode::UNPROCESSABLE_ENTITY,
+		AdminError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomSummary {
+	pub id: String,
+	pub players: usize,
+	pub playing: bool,
+	pub paused: bool,
+}
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Compares tokens in constant time, to not leak how much of the token is right
+fn tokens_match(expected: &str, given: &str) -> bool {
+	expected.len() == given.len()
+		&& expected.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn authorized(token: Option<Arc<String>>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+	warp::header::optional::<String>("authorization")
+		.and_then(move |header: Option<String>| {
+			let token = token.clone();
+			async move {
+				let token = match token {
+					Some(token) => token,
+					None => return Err(warp::reject::not_found()),
+				};
+				match header.as_deref().and_then(|header| header.strip_prefix("Bearer ")) {
+					Some(given) if tokens_match(&token, given) => Ok(()),
+					_ => Err(warp::reject::custom(Unauthorized)),
+				}
+			}
+		})
+		.untuple_one()
+}
+
+async fn handle_unauthorized(rejection: Rejection) -> Result<impl Reply, Rejection> {
+	if rejection.find::<Unauthorized>().is_some() {
+		Ok(error_reply(StatusCode::UNAUTHORIZED, "Invalid or missing admin token"))
+	} else {
+		Err(rejection)
+	}
+}
+
+fn error_reply(status: StatusCode, error: &str) -> warp::reply::WithStatus<warp::reply::Json> {
+	#[derive(Serialize)]
+	struct Error<'a> {
+		error: &'a str,
+	}
+	warp::reply::with_status(warp::reply::json(&Error { error }), status)
+}
+
+fn no_such_room() -> warp::reply::WithStatus<warp::reply::Json> {
+	error_reply(StatusCode::NOT_FOUND, "No such room")
+}
+
+async fn list_rooms(room_id: &'static str, game: Arc<RwLock<Room>>) -> Result<impl Reply, Infallible> {
+	let details = game.read().await.game().details(room_id);
+	let rooms = vec![RoomSummary {
+		id: details.id,
+		players: details.players.len(),
+		playing: details.round.is_some(),
+		paused: details.paused,
+	}];
+	Ok(warp::reply::json(&rooms))
+}
+
+async fn room_details(id: String, room_id: &'static str, game: Arc<RwLock<Room>>) -> Result<impl Reply, Infallible> {
+	if id != room_id {
+		return Ok(no_such_room());
+	}
+	let details = game.read().await.game().details(room_id);
+	Ok(warp::reply::with_status(warp::reply::json(&details), StatusCode::OK))
+}
+
+/// Applies an action to the room, and replies with its new state
+async fn apply(
+	id: String,
+	room_id: &'static str,
+	game: Arc<RwLock<Room>>,
+	action: AdminAction,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, Infallible> {
+	if id != room_id {
+		return Ok(no_such_room());
+	}
+	let game = &mut *game.write().await;
+	match game.apply_admin(action) {
+		Ok(()) => Ok(warp::reply::with_status(warp::reply::json(&game.game().details(room_id)), StatusCode::OK)),
+		Err(e) => Ok(error_reply(status(&e), &e.to_string())),
+	}
+}
+
+async fn reload(id: String, room_id: &'static str, game: Arc<RwLock<Room>>) -> Result<impl Reply, Infallible> {
+	let action = match assets::load_packs() {
+		Ok(packs) => AdminAction::ReloadPacks { packs },
+		Err(e) => return Ok(error_reply(StatusCode::UNPROCESSABLE_ENTITY, &format!("Couldn't load packs: {:#}", e))),
+	};
+	apply(id, room_id, game, action).await
+}
+
+/// Routes of the admin API, for the game exposed as `room_id`.  The API is
+/// disabled if `token` is `None`.
+pub fn routes(
+	room_id: &'static str,
+	game: Arc<RwLock<Room>>,
+	token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	let room_id = warp::any().map(move || room_id);
+	let game = warp::any().map(move || game.clone());
+	let action = |action: AdminAction| warp::any().map(move || action.clone());
+
+	let list = warp::path!("rooms")
+		.and(warp::get())
+		.and(room_id)
+		.and(game.clone())
+		.and_then(list_rooms);
+	let details = warp::path!("rooms" / String)
+		.and(warp::get())
+		.and(room_id)
+		.and(game.clone())
+		.and_then(room_details);
+	let new_round = warp::path!("rooms" / String / "round")
+		.and(warp::post())
+		.and(room_id)
+		.and(game.clone())
+		.and(action(AdminAction::NewRound))
+		.and_then(apply);
+	let end_game = warp::path!("rooms" / String / "end")
+		.and(warp::post())
+		.and(room_id)
+		.and(game.clone())
+		.and(action(AdminAction::EndGame))
+		.and_then(apply);
+	let kick = warp::path!("rooms" / String / "players" / usize)
+		.and(warp::delete())
+		.and(room_id)
+		.and(game.clone())
+		.and_then(|id, user, room_id, game| apply(id, room_id, game, AdminAction::Kick { user }));
+	let reload = warp::path!("rooms" / String / "reload")
+		.and(warp::post())
+		.and(room_id)
+		.and(game)
+		.and_then(reload);
+
+	warp::path("admin")
+		.and(authorized(token.map(Arc::new)))
+		.and(list.or(details).or(new_round).or(end_game).or(kick).or(reload))
+		.recover(handle_unauthorized)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_tokens_match() {
+		assert!(tokens_match("secret", "secret"));
+		assert!(!tokens_match("secret", "secreT"));
+		assert!(!tokens_match("secret", "secret2"));
+		assert!(!tokens_match("secret", ""));
+	}
+}
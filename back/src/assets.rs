@@ -0,0 +1,135 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, de::DeserializeOwned};
+use tokio::sync::RwLock;
+use tracing::{error, info};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use schema::{Answer, ContentRating, PackInfo, Prompt};
+
+pub use engine::Pack;
+
+use crate::admin::AdminAction;
+use crate::game::Room;
+
+/// Directory with one subdirectory per pack of cards, named after its ID
+pub static PACKS_DIR: &str = "assets/packs";
+pub static PACK_METADATA_FILE: &str = "pack.ron";
+pub static PACK_PROMPTS_FILE: &str = "prompts.ron";
+pub static PACK_ANSWERS_FILE: &str = "answers.ron";
+/// How often asset files are checked for changes
+static WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Contents of a pack's metadata file
+#[derive(Deserialize)]
+pub(crate) struct PackMetadata {
+	name: String,
+	description: String,
+	language: String,
+	rating: ContentRating,
+}
+
+pub fn load_deck<Card: DeserializeOwned>(filename: impl AsRef<Path>) -> Result<Vec<Card>, Box<dyn std::error::Error>> {
+	let file = File::open(filename)?;
+	Ok(ron::de::from_reader(file)?)
+}
+
+/// Loads a pack from its directory.  Packs may have only prompts or only
+/// answers, so either file can be missing.
+fn load_pack(dir: &Path) -> Result<Pack> {
+	let id = dir.file_name()
+		.and_then(|name| name.to_str())
+		.ok_or_else(|| anyhow!("{}: invalid pack name", dir.display()))?
+		.to_owned();
+
+	let path = dir.join(PACK_METADATA_FILE);
+	let metadata: PackMetadata = File::open(&path)
+		.map_err(anyhow::Error::from)
+		.and_then(|file| Ok(ron::de::from_reader(file)?))
+		.map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+	let path = dir.join(PACK_PROMPTS_FILE);
+	let prompts: Vec<Prompt> = if path.exists() {
+		load_deck(&path).map_err(|e| anyhow!("{}: {}", path.display(), e))?
+	} else {
+		vec![]
+	};
+
+	let path = dir.join(PACK_ANSWERS_FILE);
+	let answers: Vec<Answer> = if path.exists() {
+		load_deck(&path).map_err(|e| anyhow!("{}: {}", path.display(), e))?
+	} else {
+		vec![]
+	};
+
+	Ok(Pack {
+		info: PackInfo {
+			id,
+			name: metadata.name,
+			description: metadata.description,
+			language: metadata.language,
+			rating: metadata.rating,
+			n_prompts: prompts.len(),
+			n_answers: answers.len(),
+		},
+		prompts,
+		answers,
+	})
+}
+
+/// Directories of the packs, sorted by ID
+fn pack_dirs() -> Result<Vec<PathBuf>> {
+	let mut dirs = fs::read_dir(PACKS_DIR)
+		.map_err(|e| anyhow!("{}: {}", PACKS_DIR, e))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<Result<Vec<_>, _>>()?;
+	dirs.retain(|path| path.is_dir());
+	dirs.sort();
+	Ok(dirs)
+}
+
+/// Loads all the packs of cards from the assets directory, sorted by ID
+pub fn load_packs() -> Result<Vec<Pack>> {
+	pack_dirs()?.iter().map(|dir| load_pack(dir)).collect()
+}
+
+/// Files of the packs, with their modification time
+fn modification_times() -> Vec<(PathBuf, Option<SystemTime>)> {
+	let mut files = pack_dirs().unwrap_or_default().iter()
+		.flat_map(|dir| fs::read_dir(dir).into_iter().flatten())
+		.filter_map(|entry| entry.ok())
+		.map(|entry| (entry.path(), entry.metadata().and_then(|metadata| metadata.modified()).ok()))
+		.collect::<Vec<_>>();
+	files.sort();
+	files
+}
+
+/// Reloads the packs of the game whenever the asset files change.  Invalid
+/// files are reported, and the game keeps its current cards.
+pub async fn watch(game: Arc<RwLock<Room>>) {
+	let mut last_modified = modification_times();
+	let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+	loop {
+		interval.tick().await;
+		let modified = modification_times();
+		if modified == last_modified {
+			continue;
+		}
+		last_modified = modified;
+
+		info!("asset files changed, reloading packs");
+		let packs = match load_packs() {
+			Ok(packs) => packs,
+			Err(e) => {
+				error!(error = %e, "couldn't reload packs");
+				continue;
+			}
+		};
+		if let Err(e) = game.write().await.apply_admin(AdminAction::ReloadPacks { packs }) {
+			error!(error = %e, "couldn't reload packs");
+		}
+	}
+}
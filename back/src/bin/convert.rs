@@ -0,0 +1,120 @@
+//! Converts a deck from the community JSON format, CSV or TSV to a pack of
+//! cards, which can then be curated like the other packs.
+//!
+//! Usage: convert <deck.json|deck.csv|deck.tsv> <pack directory>
+//!                [--name <name>] [--description <text>] [--language <code>]
+//!                [--rating <Family|Teen|Mature>]
+//!
+//! The format is guessed from the extension of the deck.  Existing files of
+//! the pack are never overwritten.
+
+use anyhow::{Result, bail, Context};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use schema::ContentRating;
+
+use back::assets::{PACK_ANSWERS_FILE, PACK_METADATA_FILE, PACK_PROMPTS_FILE};
+use back::convert;
+
+static USAGE: &str = "Usage: convert <deck.json|deck.csv|deck.tsv> <pack directory> \
+	[--name <name>] [--description <text>] [--language <code>] [--rating <Family|Teen|Mature>]";
+
+struct Options {
+	deck: PathBuf,
+	pack: PathBuf,
+	name: Option<String>,
+	description: Option<String>,
+	language: String,
+	rating: ContentRating,
+}
+
+fn parse_args() -> Option<Options> {
+	let mut paths = vec![];
+	let mut name = None;
+	let mut description = None;
+	let mut language = "en".to_owned();
+	let mut rating = ContentRating::Mature;
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--name" => name = Some(args.next()?),
+			"--description" => description = Some(args.next()?),
+			"--language" => language = args.next()?,
+			"--rating" => rating = match args.next()?.to_lowercase().as_str() {
+				"family" => ContentRating::Family,
+				"teen" => ContentRating::Teen,
+				"mature" => ContentRating::Mature,
+				_ => return None,
+			},
+			_ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+			_ => return None,
+		}
+	}
+
+	if paths.len() != 2 {
+		return None;
+	}
+	let pack = paths.pop()?;
+	let deck = paths.pop()?;
+	Some(Options { deck, pack, name, description, language, rating })
+}
+
+/// Writes a new file, failing if it already exists
+fn create(path: &Path, contents: &str) -> Result<()> {
+	let mut file = OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(path)
+		.with_context(|| format!("Couldn't create {}", path.display()))?;
+	file.write_all(contents.as_bytes())?;
+	Ok(())
+}
+
+fn convert(options: &Options) -> Result<()> {
+	let src = fs::read_to_string(&options.deck)
+		.with_context(|| format!("Couldn't read {}", options.deck.display()))?;
+	let extension = options.deck.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+	let (prompts, answers) = match extension.to_lowercase().as_str() {
+		"json" => convert::from_json(&src),
+		"csv" => convert::from_csv(&src, b','),
+		"tsv" => convert::from_csv(&src, b'\t'),
+		_ => bail!("Unknown deck format {:?}, expected json, csv or tsv", extension),
+	}.with_context(|| format!("Couldn't import {}", options.deck.display()))?;
+
+	let id = options.pack.file_name().and_then(|name| name.to_str()).context("Invalid pack directory")?;
+	let name = options.name.clone().unwrap_or_else(|| id.to_owned());
+	let description = options.description.clone().unwrap_or_else(|| {
+		format!("Imported from {}", options.deck.file_name().and_then(|name| name.to_str()).unwrap_or("a deck"))
+	});
+
+	fs::create_dir_all(&options.pack)?;
+	create(&options.pack.join(PACK_METADATA_FILE), &convert::metadata_to_ron(&name, &description, &options.language, options.rating))?;
+	if !prompts.is_empty() {
+		create(&options.pack.join(PACK_PROMPTS_FILE), &convert::prompts_to_ron(&prompts))?;
+	}
+	if !answers.is_empty() {
+		create(&options.pack.join(PACK_ANSWERS_FILE), &convert::answers_to_ron(&answers))?;
+	}
+
+	println!("Imported {} prompts and {} answers to {}", prompts.len(), answers.len(), options.pack.display());
+	Ok(())
+}
+
+fn main() {
+	let options = match parse_args() {
+		Some(options) => options,
+		None => {
+			eprintln!("{}", USAGE);
+			process::exit(2);
+		}
+	};
+
+	if let Err(e) = convert(&options) {
+		eprintln!("Error: {:?}", e);
+		process::exit(1);
+	}
+}
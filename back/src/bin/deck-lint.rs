@@ -0,0 +1,50 @@
+//! Checks the packs of cards for mistakes, e.g. prompts whose number of
+//! blanks doesn't match their number of answers, or duplicate cards.
+//!
+//! Usage: deck-lint [packs directory] [--deny-warnings]
+//!
+//! Exits with an error if any problem is found, or any warning with
+//! `--deny-warnings`.
+
+use std::path::PathBuf;
+use std::process;
+
+use back::assets::PACKS_DIR;
+use back::lint::{Level, lint_packs};
+
+fn main() {
+	let mut dir = None;
+	let mut deny_warnings = false;
+	for arg in std::env::args().skip(1) {
+		match arg.as_str() {
+			"--deny-warnings" => deny_warnings = true,
+			_ if dir.is_none() && !arg.starts_with("--") => dir = Some(PathBuf::from(arg)),
+			_ => {
+				eprintln!("Usage: deck-lint [packs directory] [--deny-warnings]");
+				process::exit(2);
+			}
+		}
+	}
+	let dir = dir.unwrap_or_else(|| PathBuf::from(PACKS_DIR));
+
+	let diagnostics = match lint_packs(&dir) {
+		Ok(diagnostics) => diagnostics,
+		Err(e) => {
+			eprintln!("Error: {:#}", e);
+			process::exit(1);
+		}
+	};
+
+	for diagnostic in &diagnostics {
+		println!("{}", diagnostic);
+	}
+
+	let n_errors = diagnostics.iter().filter(|d| d.level == Level::Error).count();
+	let n_warnings = diagnostics.len() - n_errors;
+	if !diagnostics.is_empty() {
+		println!("{} errors, {} warnings", n_errors, n_warnings);
+	}
+	if n_errors > 0 || (deny_warnings && n_warnings > 0) {
+		process::exit(1);
+	}
+}
@@ -0,0 +1,409 @@
+//! Simulates many players, to see how the server holds up under load.
+//!
+//! Usage: loadtest [--rooms <n>] [--players <n>] [--duration <seconds>]
+//!                 [--think <milliseconds>] [--url <websocket URL>]...
+//!
+//! Each simulated player connects to a room over a websocket, logs in, and
+//! plays random but valid moves: submitting answers, judging, chatting and
+//! reacting, after thinking for a random time up to `--think`.  The server
+//! hosts a single room, so unless rooms are given with `--url`, one server per
+//! room is started on an ephemeral port.  These servers run on a runtime of
+//! their own, apart from the simulated players, and take as many players as
+//! `--players`, with enough cards for all of them.
+//!
+//! Once the duration is over, the latency of each query, i.e., the time until
+//! the server answered it, is reported with the throughput of messages and
+//! the number of errors.  Players turned away when logging in, e.g., because
+//! a room of a running backend is full, are counted apart, and leave.
+
+use futures::{SinkExt, StreamExt};
+use futures::future::{self, join_all};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use tokio::sync::RwLock;
+use tokio::time::delay_until;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use std::collections::{BTreeMap, HashMap};
+use std::process;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use schema::{Answer, ContentRating, Message as WsMsg, PackInfo, Prompt, Reaction, Role};
+use engine::Rules;
+
+use back::assets::Pack;
+use back::event_log::EventLog;
+use back::game::Room;
+use back::logging;
+use back::server;
+
+/// Time between chat messages of a player, to stay under the server's rate limit
+static CHAT_INTERVAL: Duration = Duration::from_secs(2);
+
+struct Options {
+	rooms: usize,
+	players: usize,
+	duration: Duration,
+	think: Duration,
+	urls: Vec<String>,
+}
+
+fn parse_args() -> Option<Options> {
+	let mut options = Options {
+		rooms: 2,
+		players: 100,
+		duration: Duration::from_secs(30),
+		think: Duration::from_millis(100),
+		urls: vec![],
+	};
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		let mut value = || args.next();
+		match arg.as_str() {
+			"--rooms" => options.rooms = value()?.parse().ok()?,
+			"--players" => options.players = value()?.parse().ok()?,
+			"--duration" => options.duration = Duration::from_secs(value()?.parse().ok()?),
+			"--think" => options.think = Duration::from_millis(value()?.parse().ok()?),
+			"--url" => options.urls.push(value()?),
+			_ => return None,
+		}
+	}
+	Some(options)
+}
+
+/// What a simulated player measured
+#[derive(Default)]
+struct Stats {
+	/// Time taken by the server to answer each query, by query
+	latencies: BTreeMap<&'static str, Vec<Duration>>,
+	sent: u64,
+	received: u64,
+	/// Valid queries that the server rejected anyway
+	rejected: BTreeMap<&'static str, u64>,
+	/// Players whose login was rejected
+	turned_away: u64,
+	/// Failed connections, and messages that couldn't be sent or read
+	errors: u64,
+	/// Queries still waiting for an answer when the test ended
+	unanswered: u64,
+}
+
+impl Stats {
+	fn merge(&mut self, other: Stats) {
+		for (query, latencies) in other.latencies {
+			self.latencies.entry(query).or_default().extend(latencies);
+		}
+		self.sent += other.sent;
+		self.received += other.received;
+		for (query, n) in other.rejected {
+			*self.rejected.entry(query).or_default() += n;
+		}
+		self.turned_away += other.turned_away;
+		self.errors += other.errors;
+		self.unanswered += other.unanswered;
+	}
+}
+
+/// State of a simulated player, as far as they know from the server's messages
+struct Player {
+	name: String,
+	rng: StdRng,
+	think: Duration,
+	role: Role,
+	/// Answers submitted this round, to tell their submission from others'
+	submitted: Vec<Answer>,
+	/// Submission reacted to this round
+	reacted_to: Option<usize>,
+	last_chat: Option<Instant>,
+	/// Next move, and when to play it
+	next_move: Option<(Instant, WsMsg)>,
+	/// When each query waiting for an answer was sent
+	pending: HashMap<&'static str, Instant>,
+	/// Whether the server rejected the player's login
+	turned_away: bool,
+	stats: Stats,
+}
+
+impl Player {
+	fn new(name: String, think: Duration) -> Player {
+		Player {
+			name,
+			rng: StdRng::from_entropy(),
+			think,
+			role: Role::Player,
+			submitted: vec![],
+			reacted_to: None,
+			last_chat: None,
+			next_move: None,
+			pending: HashMap::new(),
+			turned_away: false,
+			stats: Stats::default(),
+		}
+	}
+
+	fn plan(&mut self, msg: WsMsg) {
+		let delay = self.think.mul_f64(self.rng.gen());
+		self.next_move = Some((Instant::now() + delay, msg));
+	}
+
+	fn can_chat(&self) -> bool {
+		match self.last_chat {
+			Some(last) => last.elapsed() >= CHAT_INTERVAL,
+			None => true,
+		}
+	}
+
+	/// Records the answer to a query, `accepted` or not
+	fn answered(&mut self, query: &'static str, accepted: bool) {
+		if let Some(sent) = self.pending.remove(query) {
+			self.stats.latencies.entry(query).or_default().push(sent.elapsed());
+			if !accepted {
+				*self.stats.rejected.entry(query).or_default() += 1;
+			}
+		}
+	}
+
+	/// Reacts to a message from the server, planning the next move if there's
+	/// one to play
+	fn receive(&mut self, msg: WsMsg) {
+		match msg {
+			WsMsg::LoginAccepted => self.answered("Login", true),
+			WsMsg::LoginRejected(_) => {
+				// Not a valid query being rejected, so it's not counted with them
+				self.pending.remove("Login");
+				self.stats.turned_away += 1;
+				self.turned_away = true;
+			},
+			WsMsg::AnswerAccepted => self.answered("SubmitAnswer", true),
+			WsMsg::AnswerRejected => self.answered("SubmitAnswer", false),
+			WsMsg::JudgementRejected => self.answered("SubmitJudgement", false),
+			WsMsg::ChatRejected => self.answered("ChatSend", false),
+			WsMsg::ChatMessage(message) if message.from == self.name => self.answered("ChatSend", true),
+			WsMsg::SubmissionReactions { submission, .. } if self.reacted_to == Some(submission) => {
+				self.answered("ReactToSubmission", true);
+			},
+			WsMsg::NewRound { role, prompt, hand } => {
+				self.role = role;
+				self.submitted.clear();
+				self.reacted_to = None;
+				if self.role == Role::Player && hand.len() >= prompt.n_answers as usize {
+					let mut answers = hand.choose_multiple(&mut self.rng, prompt.n_answers as usize)
+						.cloned()
+						.collect::<Vec<_>>();
+					for answer in &mut answers {
+						if answer.blank {
+							answer.content = format!("Written by {}", self.name);
+						}
+					}
+					self.submitted = answers.clone();
+					self.plan(WsMsg::SubmitAnswer(answers));
+				} else if self.can_chat() && self.rng.gen_bool(0.25) {
+					self.last_chat = Some(Instant::now());
+					self.plan(WsMsg::ChatSend(format!("Hello from {}", self.name)));
+				}
+			},
+			WsMsg::ReadyToJudge { answers, points } => {
+				let mut submissions = answers.keys().copied().collect::<Vec<_>>();
+				submissions.shuffle(&mut self.rng);
+				if self.role == Role::Czar {
+					// Only the positions worth points can be ranked
+					let ranking = submissions.into_iter().take(points.len()).map(|id| vec![id]).collect();
+					self.plan(WsMsg::SubmitJudgement(ranking));
+				} else if let Some(&submission) = submissions.iter().find(|id| answers[id] != self.submitted) {
+					let reaction = *Reaction::ALL.choose(&mut self.rng).unwrap();
+					self.reacted_to = Some(submission);
+					self.plan(WsMsg::ReactToSubmission { submission, reaction });
+				}
+			},
+			WsMsg::RoundEnded { .. } => self.answered("SubmitJudgement", true),
+			_ => {},
+		}
+	}
+
+	/// Plays a simulated player until `end`
+	async fn play(mut self, url: String, end: Instant) -> Stats {
+		let ws = match connect_async(url.as_str()).await {
+			Ok((ws, _)) => ws,
+			Err(e) => {
+				eprintln!("{}: couldn't connect to {}: {}", self.name, url, e);
+				self.stats.errors += 1;
+				return self.stats;
+			}
+		};
+		let (mut tx, mut rx) = ws.split();
+		self.next_move = Some((Instant::now(), WsMsg::Login(self.name.clone())));
+
+		loop {
+			let when = self.next_move.as_ref().map_or(end, |(when, _)| *when);
+			let wake = tokio::time::Instant::from_std(when.min(end));
+			tokio::select! {
+				msg = rx.next() => {
+					let text = match msg {
+						Some(Ok(Message::Text(text))) => text,
+						Some(Ok(_)) => continue,
+						Some(Err(_)) | None => {
+							self.stats.errors += 1;
+							break;
+						},
+					};
+					self.stats.received += 1;
+					match serde_json::from_str(&text) {
+						Ok(msg) => self.receive(msg),
+						Err(_) => self.stats.errors += 1,
+					}
+					if self.turned_away {
+						break;
+					}
+				},
+				_ = delay_until(wake) => {
+					if Instant::now() >= end {
+						break;
+					}
+					if let Some((_, msg)) = self.next_move.take() {
+						let query = engine::query_name(&msg);
+						let text = serde_json::to_string(&msg).unwrap();
+						if tx.send(Message::text(text)).await.is_err() {
+							self.stats.errors += 1;
+							break;
+						}
+						self.stats.sent += 1;
+						self.pending.insert(query, Instant::now());
+					}
+				},
+			}
+		}
+
+		tx.close().await.ok();
+		self.stats.unanswered = self.pending.len() as u64;
+		self.stats
+	}
+}
+
+/// A pack with enough cards for `n_players` to have full hands.  The packs
+/// of `assets/` are too small for hundreds of players in a room.
+fn pack(n_players: usize) -> Pack {
+	let prompts = (0..100)
+		.map(|i| Prompt::from_template(if i % 3 == 0 { format!("Prompt {}: _ and _.", i) } else { format!("Prompt {}: _.", i) }))
+		.collect::<Vec<_>>();
+	let answers = (0..n_players * 8).map(|i| Answer::new(format!("Answer {}", i))).collect::<Vec<_>>();
+	Pack {
+		info: PackInfo {
+			id: "loadtest".to_owned(),
+			name: "Load test".to_owned(),
+			description: String::new(),
+			language: "en".to_owned(),
+			rating: ContentRating::Family,
+			n_prompts: prompts.len(),
+			n_answers: answers.len(),
+		},
+		prompts,
+		answers,
+	}
+}
+
+/// Starts a server hosting a single room for `n_players`, returning its URL
+fn start_room(n_players: usize) -> String {
+	let rules = Rules { max_players: n_players, ..Rules::default() };
+	let room = Room::with_rules(rand::random(), vec![pack(n_players)], rules, EventLog::default());
+	let routes = server::routes("main", Arc::new(RwLock::new(room)), None);
+	let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+	tokio::spawn(server);
+	format!("ws://{}/", addr)
+}
+
+/// Starts `n_rooms` servers, on a runtime of their own so that they don't
+/// share worker threads with the simulated players.  Returns their URLs.
+fn start_rooms(n_rooms: usize, n_players: usize) -> Vec<String> {
+	let (tx, rx) = mpsc::channel();
+	thread::spawn(move || {
+		let mut runtime = tokio::runtime::Builder::new()
+			.threaded_scheduler()
+			.enable_all()
+			.build()
+			.expect("Couldn't start the servers' runtime");
+		runtime.block_on(async move {
+			tx.send((0..n_rooms).map(|_| start_room(n_players)).collect()).ok();
+			future::pending::<()>().await
+		});
+	});
+	rx.recv().expect("Couldn't start the servers")
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+	let i = ((sorted.len() - 1) as f64 * p).round() as usize;
+	sorted[i]
+}
+
+fn report(stats: &Stats, elapsed: Duration) {
+	println!("{:<20} {:>8} {:>10} {:>10} {:>10} {:>10} {:>9}", "query", "count", "p50", "p90", "p99", "max", "rejected");
+	for (query, latencies) in &stats.latencies {
+		let mut sorted = latencies.clone();
+		sorted.sort_unstable();
+		println!(
+			"{:<20} {:>8} {:>10.2?} {:>10.2?} {:>10.2?} {:>10.2?} {:>9}",
+			query,
+			sorted.len(),
+			percentile(&sorted, 0.5),
+			percentile(&sorted, 0.9),
+			percentile(&sorted, 0.99),
+			sorted[sorted.len() - 1],
+			stats.rejected.get(query).copied().unwrap_or(0),
+		);
+	}
+	let seconds = elapsed.as_secs_f64();
+	println!();
+	println!("sent:       {} messages ({:.1}/s)", stats.sent, stats.sent as f64 / seconds);
+	println!("received:   {} messages ({:.1}/s)", stats.received, stats.received as f64 / seconds);
+	println!("unanswered: {} queries", stats.unanswered);
+	println!("turned away: {} players", stats.turned_away);
+	println!("errors:     {}", stats.errors);
+}
+
+#[tokio::main]
+async fn main() {
+	// The servers' own logs are noise under load, unless asked for with RUST_LOG
+	logging::init("error");
+
+	let options = match parse_args() {
+		Some(options) => options,
+		None => {
+			eprintln!("Usage: loadtest [--rooms <n>] [--players <n>] [--duration <seconds>] [--think <milliseconds>] [--url <websocket URL>]...");
+			process::exit(2);
+		}
+	};
+
+	let urls = if options.urls.is_empty() {
+		start_rooms(options.rooms, options.players)
+	} else {
+		options.urls.clone()
+	};
+	println!(
+		"Simulating {} players in each of {} rooms for {:?}",
+		options.players, urls.len(), options.duration
+	);
+
+	let start = Instant::now();
+	let end = start + options.duration;
+	let (n_players, think) = (options.players, options.think);
+	let players = urls.iter().enumerate().flat_map(|(room, url)| {
+		(0..n_players).map(move |i| {
+			let player = Player::new(format!("Bot {}-{}", room, i), think);
+			tokio::spawn(player.play(url.clone(), end))
+		})
+	});
+
+	let mut stats = Stats::default();
+	for result in join_all(players).await {
+		match result {
+			Ok(player_stats) => stats.merge(player_stats),
+			Err(_) => stats.errors += 1,
+		}
+	}
+	report(&stats, start.elapsed());
+
+	if stats.errors > 0 {
+		process::exit(1);
+	}
+}
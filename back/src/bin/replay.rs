@@ -0,0 +1,75 @@
+//! Replays a game from its event log, reconstructing its state step by step.
+//!
+//! Usage: replay <log file> [--check] [--timeline]
+//!
+//! The client and admin actions of the log are fed to a new game, created
+//! with the same seed and cards, and the decisions it takes are compared with
+//! the ones in the log.  With `--check`, the invariants of the game are also
+//! checked after every step.  With `--timeline`, every event of the log is
+//! printed.
+
+use anyhow::{Result, Context};
+use std::process;
+
+use back::event_log::{Event, read_log};
+use back::logging;
+use back::replay;
+
+struct Options {
+	path: String,
+	check: bool,
+	timeline: bool,
+}
+
+fn parse_args() -> Option<Options> {
+	let mut path = None;
+	let mut check = false;
+	let mut timeline = false;
+	for arg in std::env::args().skip(1) {
+		match arg.as_str() {
+			"--check" => check = true,
+			"--timeline" => timeline = true,
+			_ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+			_ => return None,
+		}
+	}
+	Some(Options { path: path?, check, timeline })
+}
+
+fn replay(options: &Options) -> Result<()> {
+	let entries = read_log(&options.path).context("Couldn't read log")?;
+	if let Some(Event::GameCreated { seed, .. }) = entries.first().map(|entry| &entry.event) {
+		println!("Replaying game with seed {}", seed);
+	}
+
+	replay::replay(&entries, |step, entry, game| {
+		if options.timeline {
+			println!("{:>5} {:>9}ms  {:?}", step, entry.time, entry.event);
+		}
+		if options.check {
+			game.check_invariants().with_context(|| format!("step {}: invariant violated", step))?;
+		}
+		Ok(())
+	})?;
+
+	println!("Replayed {} events successfully", entries.len());
+	Ok(())
+}
+
+fn main() {
+	// The game's own logs are noise when replaying, unless asked for with RUST_LOG
+	logging::init("error");
+
+	let options = match parse_args() {
+		Some(options) => options,
+		None => {
+			eprintln!("Usage: replay <log file> [--check] [--timeline]");
+			process::exit(2);
+		}
+	};
+
+	if let Err(e) = replay(&options) {
+		eprintln!("Error: {:?}", e);
+		process::exit(1);
+	}
+}
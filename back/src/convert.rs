@@ -0,0 +1,279 @@
+//! Conversion of decks from other formats to the RON files of a pack.
+//!
+//! Two formats are understood:
+//!
+//! - the community JSON format, with `black` cards (prompts, with a `pick`
+//!   count) and `white` cards (answers), also known as `blackCards` and
+//!   `whiteCards`.  A file may contain a single deck or a list of decks.
+//! - CSV or TSV, with one card per row: its type (`prompt`/`black` or
+//!   `answer`/`white`), its text and, for prompts, an optional number of
+//!   answers.  A header row starting with `type` is skipped.
+//!
+//! Blanks, written as any number of underscores, become a single `_`.  In
+//! prompts, backslashes and braces are escaped, as per their template syntax.
+
+use anyhow::{Result, anyhow, bail};
+use serde::Deserialize;
+
+use schema::{Answer, ContentRating, Prompt};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonDecks {
+	Many(Vec<JsonDeck>),
+	One(JsonDeck),
+}
+
+#[derive(Deserialize)]
+struct JsonDeck {
+	#[serde(default, alias = "blackCards")]
+	black: Vec<JsonBlackCard>,
+	#[serde(default, alias = "whiteCards")]
+	white: Vec<JsonWhiteCard>,
+}
+
+#[derive(Deserialize)]
+struct JsonBlackCard {
+	text: String,
+	pick: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonWhiteCard {
+	Text(String),
+	Card { text: String },
+}
+
+/// Cleans up the text of an imported card: HTML tags and entities, which are
+/// common in the JSON format, are removed, whitespace is collapsed, and
+/// blanks are shortened to a single underscore.
+fn clean_text(src: &str) -> String {
+	let mut text = String::with_capacity(src.len());
+	let mut rest = src;
+	while let Some(start) = rest.find('<') {
+		text.push_str(&rest[..start]);
+		match rest[start..].find('>') {
+			Some(end) => {
+				// Line breaks separate words, other tags are only formatting
+				if rest[start..start + end].to_lowercase().starts_with("<br") {
+					text.push(' ');
+				}
+				rest = &rest[start + end + 1..];
+			},
+			None => {
+				text.push_str(&rest[start..]);
+				rest = "";
+			},
+		}
+	}
+	text.push_str(rest);
+
+	let text = text
+		.replace("&nbsp;", " ")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&apos;", "'")
+		.replace("&reg;", "\u{AE}")
+		.replace("&trade;", "\u{2122}")
+		.replace("&copy;", "\u{A9}")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&amp;", "&");
+
+	let mut cleaned = String::with_capacity(text.len());
+	for c in text.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+		if c != '_' || !cleaned.ends_with('_') {
+			cleaned.push(c);
+		}
+	}
+	cleaned
+}
+
+/// Creates a prompt, with as many answers as blanks if not given
+fn make_prompt(text: &str, pick: Option<u8>) -> Result<Prompt> {
+	let content = clean_text(text).replace('\\', "\\\\").replace('{', "\\{");
+	if content.is_empty() {
+		bail!("empty prompt");
+	}
+	match pick {
+		Some(0) => bail!("prompt {:?} has 0 answers", content),
+		Some(pick) => Ok(Prompt::new(content, pick)),
+		None => Ok(Prompt::from_template(content)),
+	}
+}
+
+fn make_answer(text: &str) -> Result<Answer> {
+	let content = clean_text(text);
+	if content.is_empty() {
+		bail!("empty answer");
+	}
+	Ok(Answer::new(content))
+}
+
+/// Imports a deck in the community JSON format
+pub fn from_json(src: &str) -> Result<(Vec<Prompt>, Vec<Answer>)> {
+	let decks = match serde_json::from_str(src)? {
+		JsonDecks::Many(decks) => decks,
+		JsonDecks::One(deck) => vec![deck],
+	};
+
+	let mut prompts = vec![];
+	let mut answers = vec![];
+	for deck in decks {
+		for card in deck.black {
+			prompts.push(make_prompt(&card.text, card.pick)?);
+		}
+		for card in deck.white {
+			let text = match &card {
+				JsonWhiteCard::Text(text) | JsonWhiteCard::Card { text } => text,
+			};
+			answers.push(make_answer(text)?);
+		}
+	}
+	Ok((prompts, answers))
+}
+
+/// Imports a deck from CSV or TSV, depending on `delimiter`
+pub fn from_csv(src: &str, delimiter: u8) -> Result<(Vec<Prompt>, Vec<Answer>)> {
+	let mut reader = csv::ReaderBuilder::new()
+		.delimiter(delimiter)
+		.has_headers(false)
+		.flexible(true)
+		.from_reader(src.as_bytes());
+
+	let mut prompts = vec![];
+	let mut answers = vec![];
+	for (i, record) in reader.records().enumerate() {
+		let record = record?;
+		let line = record.position().map_or(i + 1, |position| position.line() as usize);
+		let field = |n| record.get(n).map(str::trim).unwrap_or("");
+
+		let result = match field(0).to_lowercase().as_str() {
+			"type" if i == 0 => continue,
+			"" if record.iter().all(|field| field.trim().is_empty()) => continue,
+			"prompt" | "black" => {
+				let pick = match field(2) {
+					"" => None,
+					pick => Some(pick.parse().map_err(|_| anyhow!("invalid number of answers {:?}", pick))?),
+				};
+				make_prompt(field(1), pick).map(|prompt| prompts.push(prompt))
+			},
+			"answer" | "white" => make_answer(field(1)).map(|answer| answers.push(answer)),
+			other => Err(anyhow!("unknown card type {:?}", other)),
+		};
+		result.map_err(|e| anyhow!("line {}: {}", line, e))?;
+	}
+	Ok((prompts, answers))
+}
+
+/// Quotes a string for RON
+fn quote(content: &str) -> String {
+	ron::ser::to_string(&content).expect("strings can always be serialized")
+}
+
+/// Writes prompts in the format of a pack's `prompts.ron`
+pub fn prompts_to_ron(prompts: &[Prompt]) -> String {
+	let mut ron = String::from("[\n");
+	for prompt in prompts {
+		ron.push_str("\tPrompt(\n");
+		ron.push_str(&format!("\t\tcontent: {},\n", quote(&prompt.content)));
+		// Only if it can't be inferred from the blanks
+		if prompt.n_answers != Prompt::from_template(prompt.content.clone()).n_answers {
+			ron.push_str(&format!("\t\tn_answers: {},\n", prompt.n_answers));
+		}
+		ron.push_str("\t),\n");
+	}
+	ron.push_str("]\n");
+	ron
+}
+
+/// Writes answers in the format of a pack's `answers.ron`
+pub fn answers_to_ron(answers: &[Answer]) -> String {
+	let mut ron = String::from("[\n");
+	for answer in answers {
+		if answer.blank {
+			ron.push_str(&format!("\tAnswer(content: {}, blank: true),\n", quote(&answer.content)));
+		} else {
+			ron.push_str(&format!("\tAnswer(content: {}),\n", quote(&answer.content)));
+		}
+	}
+	ron.push_str("]\n");
+	ron
+}
+
+/// Writes the metadata of a pack, in the format of its `pack.ron`
+pub fn metadata_to_ron(name: &str, description: &str, language: &str, rating: ContentRating) -> String {
+	format!(
+		"(\n\tname: {},\n\tdescription: {},\n\tlanguage: {},\n\trating: {},\n)\n",
+		quote(name), quote(description), quote(language), rating,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_from_json() {
+		let src = r#"{
+			"black": [
+				{"text": "Why can't I sleep at night? ____", "pick": 1},
+				{"text": "_ + _ = <i>love</i>.", "pick": 2},
+				{"text": "Is {} a \\?"}
+			],
+			"white": ["Coat hanger &amp; friends.", {"text": "A<br>bird"}]
+		}"#;
+		let (prompts, answers) = from_json(src).unwrap();
+		assert_eq!(prompts, vec![
+			Prompt::new("Why can't I sleep at night? _".to_owned(), 1),
+			Prompt::new("_ + _ = love.".to_owned(), 2),
+			Prompt::new(r"Is \{} a \\?".to_owned(), 1),
+		]);
+		assert_eq!(answers, vec![
+			Answer::new("Coat hanger & friends.".to_owned()),
+			Answer::new("A bird".to_owned()),
+		]);
+	}
+
+	#[test]
+	fn test_from_json_list_of_decks() {
+		let src = r#"[
+			{"blackCards": [{"text": "Make a haiku."}], "whiteCards": ["One"]},
+			{"whiteCards": ["Two"]}
+		]"#;
+		let (prompts, answers) = from_json(src).unwrap();
+		assert_eq!(prompts, vec![Prompt::new("Make a haiku.".to_owned(), 1)]);
+		assert_eq!(answers.len(), 2);
+	}
+
+	#[test]
+	fn test_from_csv() {
+		let src = "type,text,pick\nprompt,\"I drink to forget _, and _.\",\nwhite,\"Cards, against humanity\"\n\nblack,Three answers:,3\n";
+		let (prompts, answers) = from_csv(src, b',').unwrap();
+		assert_eq!(prompts, vec![
+			Prompt::new("I drink to forget _, and _.".to_owned(), 2),
+			Prompt::new("Three answers:".to_owned(), 3),
+		]);
+		assert_eq!(answers, vec![Answer::new("Cards, against humanity".to_owned())]);
+	}
+
+	#[test]
+	fn test_from_tsv_reports_line() {
+		let src = "answer\tFine\nquestion\tWhat?\n";
+		let error = from_csv(src, b'\t').unwrap_err().to_string();
+		assert!(error.starts_with("line 2:"), "{}", error);
+	}
+
+	#[test]
+	fn test_export_roundtrip() {
+		let prompts = vec![
+			Prompt::new("A \"quoted\" _".to_owned(), 1),
+			Prompt::new("_ and _".to_owned(), 2),
+		];
+		let answers = vec![Answer::new("Back\\slash".to_owned()), Answer::blank()];
+		assert!(!prompts_to_ron(&prompts).contains("n_answers"));
+		assert_eq!(ron::de::from_str::<Vec<Prompt>>(&prompts_to_ron(&prompts)).unwrap(), prompts);
+		assert_eq!(ron::de::from_str::<Vec<Answer>>(&answers_to_ron(&answers)).unwrap(), answers);
+	}
+}
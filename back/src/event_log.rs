@@ -0,0 +1,125 @@
+use serde::{Serialize, Deserialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+pub use engine::event::Event;
+
+/// A line of the log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+	/// Milliseconds since the log was created, which is also the time the
+	/// game was given when the event happened
+	pub time: u64,
+	#[serde(flatten)]
+	pub event: Event,
+}
+
+enum Sink {
+	None,
+	File(File),
+	Memory(Vec<LogEntry>),
+}
+
+/// Log of the events of a game, written as one JSON object per line.
+pub struct EventLog {
+	start: Instant,
+	sink: Sink,
+}
+
+impl Default for EventLog {
+	/// A log that discards all events.
+	fn default() -> Self {
+		EventLog { start: Instant::now(), sink: Sink::None }
+	}
+}
+
+impl EventLog {
+	pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Ok(EventLog { start: Instant::now(), sink: Sink::File(File::create(path)?) })
+	}
+
+	/// A log that keeps events in memory, e.g., to replay them in tests.
+	pub fn in_memory() -> Self {
+		EventLog { start: Instant::now(), sink: Sink::Memory(vec![]) }
+	}
+
+	/// Time since the log was created, in whole milliseconds as written to it.
+	/// Games are given this time, so that replaying them from the log gives
+	/// the same results.
+	pub fn now(&self) -> Duration {
+		Duration::from_millis(self.start.elapsed().as_millis() as u64)
+	}
+
+	/// Records an event that happened at `time`, as given by `now`.
+	pub fn record(&mut self, time: Duration, event: Event) {
+		let entry = LogEntry {
+			time: time.as_millis() as u64,
+			event,
+		};
+		match &mut self.sink {
+			Sink::None => {},
+			Sink::File(file) => {
+				let result = serde_json::to_string(&entry)
+					.map_err(io::Error::from)
+					.and_then(|line| writeln!(file, "{}", line));
+				if let Err(e) = result {
+					error!(error = %e, "could not write to event log");
+				}
+			},
+			Sink::Memory(entries) => entries.push(entry),
+		}
+	}
+
+	/// Entries recorded by an in-memory log.
+	pub fn entries(&self) -> &[LogEntry] {
+		match &self.sink {
+			Sink::Memory(entries) => entries,
+			_ => &[],
+		}
+	}
+}
+
+/// Reads a log written by `EventLog::to_file`.
+pub fn read_log<P: AsRef<Path>>(path: P) -> io::Result<Vec<LogEntry>> {
+	let file = BufReader::new(File::open(path)?);
+	let mut entries = vec![];
+	for (i, line) in file.lines().enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+		let entry = serde_json::from_str(&line).map_err(|e| {
+			io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+		})?;
+		entries.push(entry);
+	}
+	Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use schema::Message as WsMsg;
+
+	#[test]
+	fn test_log_entry_roundtrip() {
+		let entry = LogEntry {
+			time: 12,
+			event: Event::Received { user: 3, message: WsMsg::SubmitJudgement(vec![vec![1, 2]]) },
+		};
+		let line = serde_json::to_string(&entry).unwrap();
+		let parsed: LogEntry = serde_json::from_str(&line).unwrap();
+		assert_eq!(parsed.time, 12);
+		assert_eq!(parsed.event, entry.event);
+	}
+
+	#[test]
+	fn test_log_entry_unit_event() {
+		let line = r#"{"time":5,"event":"JudgingStarted"}"#;
+		let parsed: LogEntry = serde_json::from_str(line).unwrap();
+		assert_eq!(parsed.event, Event::JudgingStarted);
+	}
+}
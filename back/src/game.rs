@@ -0,0 +1,110 @@
+//! Hosting of a game over websockets: the engine's decisions are carried out
+//! by sending messages to clients, logging events and updating the metrics.
+
+use tokio::sync::mpsc;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use schema::Message as WsMsg;
+use engine::{Action, Effect, Game, PlayerId, Rules, query_name};
+
+use crate::admin::{AdminAction, AdminError};
+use crate::assets::Pack;
+use crate::event_log::{Event, EventLog};
+use crate::metrics;
+
+/// A game, with the clients connected to it.  Connections share the room
+/// behind a lock, held while each action is applied and its effects carried
+/// out, so that actions are atomic: e.g., two players logging in at once can't
+/// both take the last seat.
+pub struct Room {
+	game: Game,
+	clients: HashMap<PlayerId, mpsc::UnboundedSender<WsMsg>>,
+	log: EventLog,
+	/// Reshuffles of the game's decks already counted in the metrics
+	n_reshuffles: u64,
+}
+
+impl Room {
+	/// Creates a room hosting a new game, logged to `log`
+	pub fn new(seed: u64, packs: Vec<Pack>, log: EventLog) -> Room {
+		Room::with_rules(seed, packs, Rules::default(), log)
+	}
+
+	/// Creates a room like `new`, whose game is played with the given rules
+	pub fn with_rules(seed: u64, packs: Vec<Pack>, rules: Rules, mut log: EventLog) -> Room {
+		let now = log.now();
+		log.record(now, Event::GameCreated { seed, packs: packs.clone(), rules: rules.clone() });
+		Room {
+			game: Game::with_rules(seed, packs, rules),
+			clients: HashMap::new(),
+			log,
+			n_reshuffles: 0,
+		}
+	}
+
+	pub fn game(&self) -> &Game {
+		&self.game
+	}
+
+	pub fn log(&self) -> &EventLog {
+		&self.log
+	}
+
+	pub fn connect(&mut self, user_id: PlayerId, tx: mpsc::UnboundedSender<WsMsg>) {
+		self.clients.insert(user_id, tx);
+		self.apply(user_id, Action::Connect);
+	}
+
+	pub fn process_message(&mut self, user_id: PlayerId, msg: WsMsg) {
+		let _timer = metrics::MESSAGE_LATENCY.with_label_values(&[query_name(&msg)]).start_timer();
+		self.apply(user_id, Action::Message(msg));
+	}
+
+	pub fn disconnect(&mut self, user_id: PlayerId) {
+		self.clients.remove(&user_id);
+		self.apply(user_id, Action::Disconnect);
+	}
+
+	pub fn apply_admin(&mut self, action: AdminAction) -> Result<(), AdminError> {
+		let now = self.log.now();
+		let effects = self.game.apply_admin(now, action)?;
+		self.carry_out(now, effects);
+		Ok(())
+	}
+
+	fn apply(&mut self, user_id: PlayerId, action: Action) {
+		let now = self.log.now();
+		let effects = self.game.apply(now, user_id, action);
+		self.carry_out(now, effects);
+	}
+
+	/// Carries out the effects of an action applied at time `now`
+	fn carry_out(&mut self, now: Duration, effects: Vec<Effect>) {
+		for effect in effects {
+			match effect {
+				Effect::Send { to, message } => {
+					// Clients that left can't be sent anything, and it doesn't matter
+					if let Some(tx) = self.clients.get(&to) {
+						tx.send(message).ok();
+					}
+				},
+				Effect::Log(event) => {
+					match event {
+						Event::RoundStarted { .. } => metrics::ROUNDS_STARTED.inc(),
+						Event::RoundEnded { .. } => metrics::ROUNDS_COMPLETED.inc(),
+						_ => {},
+					}
+					self.log.record(now, event);
+				},
+				Effect::Rejected { query, reason } => {
+					metrics::REJECTED_ACTIONS.with_label_values(&[query, reason]).inc();
+				},
+			}
+		}
+
+		let n_reshuffles = self.game.n_reshuffles();
+		metrics::DECK_RESHUFFLES.inc_by(n_reshuffles - self.n_reshuffles);
+		self.n_reshuffles = n_reshuffles;
+	}
+}
@@ -0,0 +1,10 @@
+pub mod admin;
+pub mod assets;
+pub mod convert;
+pub mod event_log;
+pub mod game;
+pub mod lint;
+pub mod logging;
+pub mod metrics;
+pub mod replay;
+pub mod server;
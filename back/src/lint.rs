@@ -0,0 +1,391 @@
+//! Checks of the packs of cards, for mistakes that would otherwise only be
+//! found while playing.
+
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use schema::{Answer, Prompt};
+
+use crate::assets::{PackMetadata, PACK_ANSWERS_FILE, PACK_METADATA_FILE, PACK_PROMPTS_FILE};
+
+static MAX_PROMPT_LENGTH: usize = 200;
+static MAX_ANSWER_LENGTH: usize = 100;
+/// Cards this close to each other (in edits) are reported as near-duplicates
+static MAX_NEAR_DUPLICATE_DISTANCE: usize = 2;
+/// ... if they are at least this long, since short cards are often close
+static MIN_NEAR_DUPLICATE_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+	Warning,
+	Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+	pub path: PathBuf,
+	pub line: Option<usize>,
+	pub level: Level,
+	pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.path.display())?;
+		if let Some(line) = self.line {
+			write!(f, ":{}", line)?;
+		}
+		let level = match self.level {
+			Level::Warning => "warning",
+			Level::Error => "error",
+		};
+		write!(f, ": {}: {}", level, self.message)
+	}
+}
+
+/// A card and where it was found
+#[derive(Debug)]
+struct Card {
+	path: PathBuf,
+	line: Option<usize>,
+	content: String,
+}
+
+impl Card {
+	fn location(&self) -> String {
+		match self.line {
+			Some(line) => format!("{}:{}", self.path.display(), line),
+			None => self.path.display().to_string(),
+		}
+	}
+}
+
+#[derive(Default)]
+struct Linter {
+	diagnostics: Vec<Diagnostic>,
+	prompts: Vec<Card>,
+	answers: Vec<Card>,
+}
+
+impl Linter {
+	fn report(&mut self, path: &Path, line: Option<usize>, level: Level, message: String) {
+		self.diagnostics.push(Diagnostic { path: path.to_owned(), line, level, message });
+	}
+
+	/// Parses a file of the pack, reporting syntax errors.  Also returns the
+	/// line of each card.
+	fn parse<T: DeserializeOwned>(&mut self, path: &Path, src: &str) -> Option<(T, Vec<usize>)> {
+		match ron::de::from_str(src) {
+			Ok(value) => Some((value, content_lines(src))),
+			Err(e) => {
+				self.report(path, Some(e.position.line), Level::Error, e.code.to_string());
+				None
+			},
+		}
+	}
+
+	fn check_text(&mut self, path: &Path, line: Option<usize>, content: &str, max_length: usize) {
+		// Double spaces are allowed, e.g. between sentences
+		if content.trim() != content || content.chars().any(char::is_control) {
+			self.report(path, line, Level::Warning, format!("stray whitespace in {:?}", content));
+		}
+		let length = content.chars().count();
+		if length > max_length {
+			self.report(path, line, Level::Warning, format!("card is {} characters long, more than {}", length, max_length));
+		}
+	}
+
+	fn lint_prompts(&mut self, path: &Path, src: &str) {
+		let (prompts, lines) = match self.parse::<Vec<Prompt>>(path, src) {
+			Some(parsed) => parsed,
+			None => return,
+		};
+
+		for (i, prompt) in prompts.into_iter().enumerate() {
+			let line = lines.get(i).copied();
+			if prompt.content.trim().is_empty() {
+				self.report(path, line, Level::Error, "empty prompt".to_owned());
+				continue;
+			}
+			self.check_text(path, line, &prompt.content, MAX_PROMPT_LENGTH);
+
+			let n_blanks = prompt.n_blanks();
+			if n_blanks > 0 && n_blanks != prompt.n_answers as usize {
+				self.report(path, line, Level::Error, format!(
+					"prompt has {} blanks, but n_answers is {}", n_blanks, prompt.n_answers
+				));
+			} else if n_blanks == 0 && prompt.n_answers > 1 {
+				self.report(path, line, Level::Warning, format!(
+					"prompt has no blanks, but n_answers is {}", prompt.n_answers
+				));
+			}
+
+			self.prompts.push(Card { path: path.to_owned(), line, content: prompt.content });
+		}
+	}
+
+	fn lint_answers(&mut self, path: &Path, src: &str) {
+		let (answers, lines) = match self.parse::<Vec<Answer>>(path, src) {
+			Some(parsed) => parsed,
+			None => return,
+		};
+
+		for (i, answer) in answers.into_iter().enumerate() {
+			let line = lines.get(i).copied();
+			if answer.blank {
+				// Blank cards are all alike
+				if !answer.content.is_empty() {
+					self.report(path, line, Level::Error, "blank card has content".to_owned());
+				}
+				continue;
+			}
+			if answer.content.trim().is_empty() {
+				self.report(path, line, Level::Error, "empty answer".to_owned());
+				continue;
+			}
+			self.check_text(path, line, &answer.content, MAX_ANSWER_LENGTH);
+
+			self.answers.push(Card { path: path.to_owned(), line, content: answer.content });
+		}
+	}
+
+	fn lint_pack(&mut self, dir: &Path) {
+		let path = dir.join(PACK_METADATA_FILE);
+		match fs::read_to_string(&path) {
+			Ok(src) => {
+				self.parse::<PackMetadata>(&path, &src);
+			},
+			Err(e) => self.report(&path, None, Level::Error, e.to_string()),
+		}
+
+		let mut has_cards = false;
+		for (file, is_prompts) in [(PACK_PROMPTS_FILE, true), (PACK_ANSWERS_FILE, false)].iter() {
+			let path = dir.join(file);
+			if !path.exists() {
+				continue;
+			}
+			has_cards = true;
+			match fs::read_to_string(&path) {
+				Ok(src) if *is_prompts => self.lint_prompts(&path, &src),
+				Ok(src) => self.lint_answers(&path, &src),
+				Err(e) => self.report(&path, None, Level::Error, e.to_string()),
+			}
+		}
+		if !has_cards {
+			self.report(dir, None, Level::Warning, "pack has no cards".to_owned());
+		}
+	}
+
+	/// Reports cards that are the same, or nearly, as a card found before
+	fn check_duplicates(&mut self, prompts: bool) {
+		let cards = if prompts { &self.prompts } else { &self.answers };
+		let mut diagnostics = vec![];
+
+		let mut seen = HashMap::new();
+		let normalized = cards.iter().map(|card| normalize(&card.content)).collect::<Vec<_>>();
+		for (i, card) in cards.iter().enumerate() {
+			if let Some(&j) = seen.get(&card.content) {
+				let original: &Card = &cards[j];
+				// Duplicates across packs are only a problem if both are chosen
+				let level = if original.path.parent() == card.path.parent() { Level::Error } else { Level::Warning };
+				diagnostics.push(Diagnostic {
+					path: card.path.clone(),
+					line: card.line,
+					level,
+					message: format!("duplicate of the card at {}", original.location()),
+				});
+				continue;
+			}
+			seen.insert(&card.content, i);
+
+			let similar = (0..i).find(|&j| {
+				cards[j].content != card.content && is_near_duplicate(&normalized[i], &normalized[j])
+			});
+			if let Some(j) = similar {
+				diagnostics.push(Diagnostic {
+					path: card.path.clone(),
+					line: card.line,
+					level: Level::Warning,
+					message: format!("{:?} is nearly the same as {:?} at {}", card.content, cards[j].content, cards[j].location()),
+				});
+			}
+		}
+
+		self.diagnostics.extend(diagnostics);
+	}
+}
+
+/// Lowercases text and strips punctuation, to compare cards
+fn normalize(content: &str) -> String {
+	content.chars()
+		.filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '_')
+		.flat_map(char::to_lowercase)
+		.collect::<String>()
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+fn is_near_duplicate(a: &str, b: &str) -> bool {
+	let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+	if a.len().min(b.len()) < MIN_NEAR_DUPLICATE_LENGTH {
+		return a == b;
+	}
+	if a.len().max(b.len()) - a.len().min(b.len()) > MAX_NEAR_DUPLICATE_DISTANCE {
+		return false;
+	}
+	edit_distance(&a, &b) <= MAX_NEAR_DUPLICATE_DISTANCE
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+	let mut previous = (0..=b.len()).collect::<Vec<_>>();
+	let mut current = vec![0; b.len() + 1];
+	for (i, ca) in a.iter().enumerate() {
+		current[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+			current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+		}
+		std::mem::swap(&mut previous, &mut current);
+	}
+	previous[b.len()]
+}
+
+/// Lines of the `content` fields of a RON file, i.e., the line of each card,
+/// skipping strings and comments.
+fn content_lines(src: &str) -> Vec<usize> {
+	let mut lines = vec![];
+	let mut line = 1;
+	let mut chars = src.char_indices().peekable();
+
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'\n' => line += 1,
+			'"' => {
+				while let Some((_, c)) = chars.next() {
+					match c {
+						'\\' => { chars.next(); },
+						'\n' => line += 1,
+						'"' => break,
+						_ => {},
+					}
+				}
+			},
+			'/' if matches!(chars.peek(), Some((_, '/'))) => {
+				while let Some(&(_, c)) = chars.peek() {
+					if c == '\n' {
+						break;
+					}
+					chars.next();
+				}
+			},
+			'/' if matches!(chars.peek(), Some((_, '*'))) => {
+				chars.next();
+				let mut previous = ' ';
+				for (_, c) in &mut chars {
+					if c == '\n' {
+						line += 1;
+					}
+					if previous == '*' && c == '/' {
+						break;
+					}
+					previous = c;
+				}
+			},
+			'c' if src[i..].starts_with("content")
+				&& !src[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_') => {
+				let rest = src[i + "content".len()..].trim_start();
+				if rest.starts_with(':') {
+					lines.push(line);
+				}
+			},
+			_ => {},
+		}
+	}
+
+	lines
+}
+
+/// Checks all the packs of a directory, returning diagnostics ordered by file
+pub fn lint_packs(dir: &Path) -> Result<Vec<Diagnostic>> {
+	let mut pack_dirs = fs::read_dir(dir)
+		.map_err(|e| anyhow!("{}: {}", dir.display(), e))?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<Result<Vec<_>, _>>()?;
+	pack_dirs.retain(|path| path.is_dir());
+	pack_dirs.sort();
+
+	let mut linter = Linter::default();
+	for pack_dir in pack_dirs {
+		linter.lint_pack(&pack_dir);
+	}
+	linter.check_duplicates(true);
+	linter.check_duplicates(false);
+
+	let mut diagnostics = linter.diagnostics;
+	diagnostics.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+	Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn messages(linter: &Linter) -> Vec<(Option<usize>, Level, &str)> {
+		linter.diagnostics.iter().map(|d| (d.line, d.level, d.message.as_str())).collect()
+	}
+
+	#[test]
+	fn test_content_lines() {
+		let src = "[\n\t// content: not a card\n\tAnswer(content: \"a\\\"content: b\"),\n\t/* content:\n*/ Answer(\n\t\tcontent: \"c\",\n\t),\n]";
+		assert_eq!(content_lines(src), vec![3, 6]);
+	}
+
+	#[test]
+	fn test_lint_prompts_blanks() {
+		let mut linter = Linter::default();
+		let src = "[\n\tPrompt(content: \"_ and _\", n_answers: 2),\n\tPrompt(content: \"Just _\", n_answers: 2),\n\tPrompt(content: \"Haiku.\", n_answers: 3),\n\tPrompt(content: \"my\\\\_var is {}\", n_answers: 1),\n]";
+		linter.lint_prompts(Path::new("prompts.ron"), src);
+		assert_eq!(messages(&linter), vec![
+			(Some(3), Level::Error, "prompt has 1 blanks, but n_answers is 2"),
+			(Some(4), Level::Warning, "prompt has no blanks, but n_answers is 3"),
+		]);
+	}
+
+	#[test]
+	fn test_lint_answers_syntax_error() {
+		let mut linter = Linter::default();
+		linter.lint_answers(Path::new("answers.ron"), "[\n\tAnswer(content: \"a\"),\n\tAnswer(\n]");
+		assert_eq!(linter.diagnostics.len(), 1);
+		assert_eq!(linter.diagnostics[0].level, Level::Error);
+		assert_eq!(linter.diagnostics[0].line, Some(4));
+	}
+
+	#[test]
+	fn test_lint_answers_text() {
+		let mut linter = Linter::default();
+		let src = format!("[\n\tAnswer(content: \" Padded\"),\n\tAnswer(content: \"{}\"),\n\tAnswer(content: \"\", blank: true),\n]", "a".repeat(101));
+		linter.lint_answers(Path::new("answers.ron"), &src);
+		assert_eq!(messages(&linter), vec![
+			(Some(2), Level::Warning, "stray whitespace in \" Padded\""),
+			(Some(3), Level::Warning, "card is 101 characters long, more than 100"),
+		]);
+	}
+
+	#[test]
+	fn test_duplicates() {
+		let mut linter = Linter::default();
+		let src = "[\n\tAnswer(content: \"A pumpkin spice latte.\"),\n\tAnswer(content: \"A pumpkin spice latte.\"),\n\tAnswer(content: \"a pumpkin spice lattes\"),\n\tAnswer(content: \"Dogs\"),\n\tAnswer(content: \"Cats\"),\n]";
+		linter.lint_answers(Path::new("pack/answers.ron"), src);
+		linter.check_duplicates(false);
+		assert_eq!(messages(&linter), vec![
+			(Some(3), Level::Error, "duplicate of the card at pack/answers.ron:2"),
+			(Some(4), Level::Warning, "\"a pumpkin spice lattes\" is nearly the same as \"A pumpkin spice latte.\" at pack/answers.ron:2"),
+		]);
+	}
+}
@@ -0,0 +1,26 @@
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable with the output format of the logs, `text` or `json`
+static LOG_FORMAT_VAR: &str = "LOG_FORMAT";
+
+/// Install a global subscriber printing logs to stderr.
+///
+/// Log levels are filtered with `RUST_LOG` (e.g. `RUST_LOG=back=debug`), falling back to
+/// `default_filter` if unset.  Setting `LOG_FORMAT=json` prints one JSON object per line,
+/// including the fields of the enclosing spans.
+pub fn init(default_filter: &str) {
+	let filter = EnvFilter::try_from_default_env()
+		.unwrap_or_else(|_| EnvFilter::new(default_filter));
+	let builder = tracing_subscriber::fmt()
+		.with_env_filter(filter)
+		.with_writer(std::io::stderr);
+
+	match std::env::var(LOG_FORMAT_VAR).as_deref() {
+		Ok("json") => builder.json().with_current_span(true).with_span_list(true).init(),
+		Ok("text") | Err(_) => builder.init(),
+		Ok(other) => {
+			builder.init();
+			tracing::warn!(format = other, "unknown log format, expected `text` or `json`");
+		}
+	}
+}
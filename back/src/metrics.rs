@@ -0,0 +1,78 @@
+//! Prometheus metrics, exposed over HTTP on `/metrics`
+
+use once_cell::sync::Lazy;
+use prometheus::{
+	Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+	register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+	register_int_gauge_vec,
+};
+
+pub static CONNECTED_SOCKETS: Lazy<IntGauge> = Lazy::new(|| register_int_gauge!(
+	"cards_connected_sockets",
+	"Number of open websocket connections"
+).unwrap());
+
+pub static PLAYERS: Lazy<IntGaugeVec> = Lazy::new(|| register_int_gauge_vec!(
+	"cards_players",
+	"Number of logged in players, per room",
+	&["room"]
+).unwrap());
+
+pub static ROUNDS_STARTED: Lazy<IntCounter> = Lazy::new(|| register_int_counter!(
+	"cards_rounds_started_total",
+	"Number of rounds started, including restarted ones"
+).unwrap());
+
+pub static ROUNDS_COMPLETED: Lazy<IntCounter> = Lazy::new(|| register_int_counter!(
+	"cards_rounds_completed_total",
+	"Number of rounds that were judged by the Czar"
+).unwrap());
+
+pub static REJECTED_ACTIONS: Lazy<IntCounterVec> = Lazy::new(|| register_int_counter_vec!(
+	"cards_rejected_actions_total",
+	"Number of invalid queries from clients, by query and reason",
+	&["query", "reason"]
+).unwrap());
+
+pub static MESSAGE_LATENCY: Lazy<HistogramVec> = Lazy::new(|| register_histogram_vec!(
+	"cards_message_processing_seconds",
+	"Time taken to process a message from a client, by query",
+	&["query"],
+	vec![0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1]
+).unwrap());
+
+pub static DECK_RESHUFFLES: Lazy<IntCounter> = Lazy::new(|| register_int_counter!(
+	"cards_deck_reshuffles_total",
+	"Number of times a deck ran out of cards and the discard pile was reshuffled"
+).unwrap());
+
+/// Render all metrics in the Prometheus text format
+pub fn render() -> String {
+	// Metrics are registered lazily, make sure they all show up even if still zero
+	Lazy::force(&CONNECTED_SOCKETS);
+	Lazy::force(&PLAYERS);
+	Lazy::force(&ROUNDS_STARTED);
+	Lazy::force(&ROUNDS_COMPLETED);
+	Lazy::force(&REJECTED_ACTIONS);
+	Lazy::force(&MESSAGE_LATENCY);
+	Lazy::force(&DECK_RESHUFFLES);
+
+	let mut buffer = Vec::new();
+	TextEncoder::new()
+		.encode(&prometheus::gather(), &mut buffer)
+		.expect("metrics can always be encoded as text");
+	String::from_utf8(buffer).expect("metrics are valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_render_includes_labelled_metrics() {
+		REJECTED_ACTIONS.with_label_values(&["SubmitAnswer", "game is paused"]).inc();
+		let text = render();
+		assert!(text.contains("# TYPE cards_rejected_actions_total counter"));
+		assert!(text.contains(r#"cards_rejected_actions_total{query="SubmitAnswer",reason="game is paused"}"#));
+	}
+}
@@ -0,0 +1,65 @@
+//! Replay of a game from its event log: the client and admin actions of the
+//! log are fed to a new game, created with the same seed, cards and rules, and
+//! the decisions it takes are compared with the ones in the log.
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+use engine::{Action, Effect, Game};
+
+use crate::event_log::{Event, LogEntry};
+
+/// Replays the game logged in `entries`, calling `on_step` after each of
+/// them, e.g., to check the game's invariants.  Returns the replayed game, or
+/// an error at the first step where it diverged from the logged one.
+pub fn replay(
+	entries: &[LogEntry],
+	mut on_step: impl FnMut(usize, &LogEntry, &Game) -> Result<()>,
+) -> Result<Game> {
+	let mut game = match entries.first().map(|entry| &entry.event) {
+		Some(Event::GameCreated { seed, packs, rules }) => Game::with_rules(*seed, packs.clone(), rules.clone()),
+		_ => bail!("Log doesn't start with GameCreated"),
+	};
+
+	// Events logged by the replayed game.  Its creation is logged by the
+	// server rather than by the game itself.
+	let mut replayed = vec![entries[0].event.clone()];
+
+	for (step, entry) in entries.iter().enumerate() {
+		// The game is given the time the action happened, as logged
+		let now = Duration::from_millis(entry.time);
+		let effects = match &entry.event {
+			Event::Connected { user } => game.apply(now, *user, Action::Connect),
+			Event::Received { user, message } => game.apply(now, *user, Action::Message(message.clone())),
+			Event::Disconnected { user } => game.apply(now, *user, Action::Disconnect),
+			Event::Admin { action } => match game.apply_admin(now, action.clone()) {
+				Ok(effects) => effects,
+				Err(e) => bail!("step {}: replay diverged, the logged admin action was rejected: {}", step, e),
+			},
+			// Decisions of the server are compared at the end
+			_ => vec![],
+		};
+
+		// Messages to clients are discarded
+		replayed.extend(effects.into_iter().filter_map(|effect| match effect {
+			Effect::Log(event) => Some(event),
+			_ => None,
+		}));
+
+		on_step(step, entry, &game)?;
+	}
+
+	// The replayed game should have taken the same decisions as the logged one
+	for (step, entry) in entries.iter().enumerate() {
+		match replayed.get(step) {
+			Some(event) if *event == entry.event => {},
+			Some(event) => bail!("step {}: replay diverged\n  logged:   {:?}\n  replayed: {:?}", step, entry.event, event),
+			None => bail!("step {}: replay ended early, logged {:?}", step, entry.event),
+		}
+	}
+	if replayed.len() > entries.len() {
+		bail!("replay continued after the log ended, with {:?}", replayed[entries.len()]);
+	}
+
+	Ok(game)
+}
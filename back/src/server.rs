@@ -0,0 +1,141 @@
+//! Routes of the server: the websocket clients play on, and the HTTP API
+//! around it.
+
+use futures::{FutureExt, StreamExt};
+use warp::{Filter, Rejection, Reply};
+use warp::ws::{Message, WebSocket};
+use tokio::sync::{mpsc, RwLock};
+use std::sync::{
+	Arc,
+	atomic::{AtomicUsize, Ordering},
+};
+use std::convert::Infallible;
+use tracing::{Instrument, info, info_span, warn};
+
+use schema::Message as WsMsg;
+
+use crate::admin;
+use crate::game::Room;
+use crate::metrics;
+
+static NEXT_USER_ID: AtomicUsize = AtomicUsize::new(1);
+
+async fn user_connected(room_id: &'static str, game: Arc<RwLock<Room>>, socket: WebSocket) {
+	let my_id = NEXT_USER_ID.fetch_add(1, Ordering::Relaxed);
+
+	let room = info_span!("room", id = room_id);
+	let connection = info_span!(parent: &room, "connection", user = my_id);
+	handle_connection(game, socket, my_id).instrument(connection).await
+}
+
+async fn handle_connection(game: Arc<RwLock<Room>>, socket: WebSocket, my_id: usize) {
+	info!("user connected");
+	metrics::CONNECTED_SOCKETS.inc();
+
+	let (tx, mut rx) = socket.split();
+
+	// Manage outgoing messages to this user
+	let tx = {
+		let (tx2, rx) = mpsc::unbounded_channel();
+		tokio::task::spawn(rx.map(|msg| {
+			Ok(Message::text(serde_json::to_string(&msg).unwrap()))
+		}).forward(tx).map(move |result| {
+			if let Err(e) = result {
+				warn!(error = %e, "websocket send error");
+			}
+		}).in_current_span());
+		tx2
+	};
+
+	game.write().await.connect(my_id, tx);
+
+	// Manage incoming messages from this user
+	while let Some(result) = rx.next().await {
+		let msg = match result {
+			Ok(msg) => msg,
+			Err(e) => {
+				warn!(error = %e, "websocket error");
+				break;
+			}
+		};
+
+		if let Ok(text) = msg.to_str() {
+			if let Ok(response) = serde_json::from_str::<WsMsg>(text) {
+				game.write().await.process_message(my_id, response);
+			} else {
+				warn!(text, "cannot read message");
+			}
+		}
+	}
+
+	info!("user disconnected");
+	metrics::CONNECTED_SOCKETS.dec();
+	game.write().await.disconnect(my_id);
+}
+
+async fn round_history(id: String, room_id: &'static str, game: Arc<RwLock<Room>>) -> Result<impl Reply, Infallible> {
+	if id == room_id {
+		let history = game.read().await.game().history().to_vec();
+		Ok(warp::reply::with_status(warp::reply::json(&history), warp::http::StatusCode::OK))
+	} else {
+		Ok(warp::reply::with_status(warp::reply::json(&"No such room"), warp::http::StatusCode::NOT_FOUND))
+	}
+}
+
+async fn render_metrics(room_id: &'static str, game: Arc<RwLock<Room>>) -> Result<impl Reply, Infallible> {
+	// Player counts are read when scraped, rather than tracked on every join and leave
+	metrics::PLAYERS.with_label_values(&[room_id]).set(game.read().await.game().n_players() as i64);
+	Ok(warp::reply::with_header(
+		metrics::render(),
+		"content-type",
+		"text/plain; version=0.0.4",
+	))
+}
+
+// async fn login(username: String, game: Arc<RwLock<Game>>) -> Result<impl warp::Reply, Infallible> {
+// 	Ok(warp::reply::json(&WsMsg::LoginAccepted));
+// 	Ok(warp::reply::json(&WsMsg::LoginRejected(LoginRejectedReason::GameIsFull)))
+// }
+
+/// All routes of the server, for the game exposed as `room_id`.  The admin
+/// API is disabled if `admin_token` is `None`.
+pub fn routes(
+	room_id: &'static str,
+	game: Arc<RwLock<Room>>,
+	admin_token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	let admin = admin::routes(room_id, game.clone(), admin_token);
+	let room_id = warp::any().map(move || room_id);
+	let game_state = warp::any().map(move || game.clone());
+
+	// warp::path!("login")
+	// 	.and(warp::post())
+	// 	.and(warp::body::json())
+	// 	.and(game_state.clone())
+	// 	.and_then(login);
+
+	let login = warp::path::end()
+		.map(|| {
+			"Hello World!"
+		});
+	let game = warp::path::end()
+		.and(warp::ws())
+		.and(room_id)
+		.and(game_state.clone())
+		.map(|ws: warp::ws::Ws, room_id, game| {
+			ws.on_upgrade(move |socket| user_connected(room_id, game, socket))
+		});
+	let history = warp::path!("rooms" / String / "history")
+		.and(warp::get())
+		.and(room_id)
+		.and(game_state.clone())
+		.and_then(round_history);
+	let metrics = warp::path!("metrics")
+		.and(warp::get())
+		.and(room_id)
+		.and(game_state)
+		.and_then(render_metrics);
+
+	// Match any request and return hello world!
+	game.or(history).or(metrics).or(admin).or(login)
+}
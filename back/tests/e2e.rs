@@ -0,0 +1,444 @@
+//! End-to-end tests: the server is started on an ephemeral port, and scripted
+//! clients play through it over websockets, like browsers would.
+
+use futures::{SinkExt, StreamExt};
+use futures::future::join_all;
+use tokio::net::TcpStream;
+use tokio::sync::RwLock;
+use tokio::time::timeout;
+use tokio_tungstenite::{WebSocketStream, connect_async, tungstenite::Message};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use schema::{Answer, ContentRating, LoginRejectedReason, Message as WsMsg, PackInfo, Prompt, Role};
+
+use engine::Rules;
+
+use back::assets::Pack;
+use back::event_log::EventLog;
+use back::game::Room;
+use back::replay::replay;
+use back::server;
+
+/// How long a client waits for an expected message before failing the test
+static RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A server hosting a single game, running for the duration of a test
+struct Server {
+	addr: SocketAddr,
+	room: Arc<RwLock<Room>>,
+}
+
+impl Server {
+	fn start() -> Server {
+		Server::with_rules(Rules::default())
+	}
+
+	fn with_rules(rules: Rules) -> Server {
+		let prompts = (0..20).map(|i| Prompt::from_template(format!("Prompt {}: _.", i))).collect::<Vec<_>>();
+		let answers = (0..60).map(|i| Answer::new(format!("Answer {}", i))).collect::<Vec<_>>();
+		let pack = Pack {
+			info: PackInfo {
+				id: "test".to_owned(),
+				name: "Test".to_owned(),
+				description: String::new(),
+				language: "en".to_owned(),
+				rating: ContentRating::Family,
+				n_prompts: prompts.len(),
+				n_answers: answers.len(),
+			},
+			prompts,
+			answers,
+		};
+
+		let room = Arc::new(RwLock::new(Room::with_rules(0, vec![pack], rules, EventLog::in_memory())));
+		let routes = server::routes("test", room.clone(), None);
+		let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+		tokio::spawn(server);
+		Server { addr, room }
+	}
+
+	async fn connect(&self, name: &str) -> Client {
+		let (ws, _) = connect_async(format!("ws://{}/", self.addr)).await.expect("Couldn't connect");
+		Client { name: name.to_owned(), ws }
+	}
+
+	/// Connects a client and logs in with `name`, which must be accepted
+	async fn join(&self, name: &str) -> Client {
+		let mut client = self.connect(name).await;
+		client.send(WsMsg::Login(name.to_owned())).await;
+		client.expect(|msg| matches!(msg, WsMsg::LoginAccepted)).await;
+		client
+	}
+
+	async fn check_invariants(&self) {
+		self.room.read().await.game().check_invariants().unwrap();
+	}
+
+	/// Replays the game from its log, which should lead to the same decisions
+	async fn check_replay(&self) {
+		let entries = self.room.read().await.log().entries().to_vec();
+		assert!(!entries.is_empty());
+		replay(&entries, |_, _, game| game.check_invariants()).unwrap();
+	}
+
+	async fn n_players(&self) -> usize {
+		self.room.read().await.game().n_players()
+	}
+}
+
+/// A scripted client, speaking the same protocol as the frontend
+struct Client {
+	name: String,
+	ws: WebSocketStream<TcpStream>,
+}
+
+impl Client {
+	async fn send(&mut self, msg: WsMsg) {
+		let text = serde_json::to_string(&msg).unwrap();
+		self.ws.send(Message::text(text)).await.expect("Couldn't send message");
+	}
+
+	async fn recv(&mut self) -> WsMsg {
+		loop {
+			let msg = timeout(RECEIVE_TIMEOUT, self.ws.next()).await
+				.expect("Timed out waiting for a message")
+				.expect("Connection closed")
+				.expect("Websocket error");
+			if let Message::Text(text) = msg {
+				return serde_json::from_str(&text).expect("Invalid message from server");
+			}
+		}
+	}
+
+	/// Receives messages until one matches, skipping the others
+	async fn expect(&mut self, matches: impl Fn(&WsMsg) -> bool) -> WsMsg {
+		loop {
+			let msg = self.recv().await;
+			if matches(&msg) {
+				return msg;
+			}
+		}
+	}
+
+	/// Waits for the next round, returning the client's role and hand
+	async fn new_round(&mut self) -> (Role, Vec<Answer>) {
+		match self.expect(|msg| matches!(msg, WsMsg::NewRound { .. })).await {
+			WsMsg::NewRound { role, hand, .. } => (role, hand),
+			_ => unreachable!(),
+		}
+	}
+
+	async fn close(mut self) {
+		self.ws.close(None).await.ok();
+	}
+}
+
+/// Joins three players, who start a round, and returns them with their hands,
+/// the Czar first
+async fn start_round(server: &Server) -> Vec<(Client, Vec<Answer>)> {
+	let mut clients = vec![];
+	for name in &["Alice", "Bob", "Carol"] {
+		clients.push(server.join(name).await);
+	}
+	next_round(clients).await
+}
+
+/// Waits for every client to be told about the next round, and returns them
+/// with their hands, the Czar first
+async fn next_round(clients: Vec<Client>) -> Vec<(Client, Vec<Answer>)> {
+	let mut players = vec![];
+	let mut n_czars = 0;
+	for mut client in clients {
+		let (role, hand) = client.new_round().await;
+		assert_eq!(hand.len(), 4);
+		if role == Role::Czar {
+			n_czars += 1;
+			players.insert(0, (client, hand));
+		} else {
+			players.push((client, hand));
+		}
+	}
+	assert_eq!(n_czars, 1);
+	players
+}
+
+/// Players other than the Czar submit their first card, and everyone waits
+/// for judging to start.  Returns the submitting players' IDs.
+async fn submit_answers(players: &mut [(Client, Vec<Answer>)]) -> Vec<usize> {
+	for (client, hand) in &mut players[1..] {
+		client.send(WsMsg::SubmitAnswer(vec![hand[0].clone()])).await;
+		client.expect(|msg| matches!(msg, WsMsg::AnswerAccepted)).await;
+	}
+
+	let mut submissions = None;
+	for (client, _) in players.iter_mut() {
+		match client.expect(|msg| matches!(msg, WsMsg::ReadyToJudge { .. })).await {
+			WsMsg::ReadyToJudge { answers, .. } => {
+				let mut ids = answers.keys().copied().collect::<Vec<_>>();
+				ids.sort_unstable();
+				assert!(submissions.is_none() || submissions.as_ref() == Some(&ids));
+				submissions = Some(ids);
+			},
+			_ => unreachable!(),
+		}
+	}
+	submissions.unwrap()
+}
+
+#[tokio::test]
+async fn test_login_rejected() {
+	let server = Server::start();
+	let _alice = server.join("Alice").await;
+
+	let mut client = server.connect("Alice").await;
+	client.send(WsMsg::Login("Alice".to_owned())).await;
+	assert_eq!(client.recv().await, WsMsg::LoginRejected(LoginRejectedReason::UsernameIsTaken));
+
+	let _bob = server.join("Bob").await;
+	let _carol = server.join("Carol").await;
+	let mut client = server.connect("Dave").await;
+	client.send(WsMsg::Login("Dave".to_owned())).await;
+	assert_eq!(client.recv().await, WsMsg::LoginRejected(LoginRejectedReason::GameIsFull));
+
+	assert_eq!(server.n_players().await, 3);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_judging() {
+	let server = Server::start();
+	let mut players = start_round(&server).await;
+	let submissions = submit_answers(&mut players).await;
+
+	// The Czar ranks the submissions, and everyone sees the results
+	let ranking = submissions.iter().rev().map(|id| vec![*id]).collect::<Vec<_>>();
+	players[0].0.send(WsMsg::SubmitJudgement(ranking)).await;
+	for (client, _) in &mut players {
+		match client.expect(|msg| matches!(msg, WsMsg::RoundEnded { .. })).await {
+			WsMsg::RoundEnded { ranking, scores, .. } => {
+				assert_eq!(ranking.len(), 2);
+				assert_eq!(ranking[0].submission, submissions[1]);
+				assert_eq!(ranking[0].points, 3);
+				assert_eq!(ranking[1].points, 2);
+				assert_eq!(scores.values().sum::<u64>(), 5);
+			},
+			_ => unreachable!(),
+		}
+	}
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_czar_rotation() {
+	let server = Server::start();
+	let mut players = start_round(&server).await;
+
+	// Every player is Czar once, in turn
+	let mut czars = vec![];
+	for _ in 0..3 {
+		czars.push(players[0].0.name.clone());
+		let submissions = submit_answers(&mut players).await;
+		let ranking = submissions.iter().map(|id| vec![*id]).collect();
+		players[0].0.send(WsMsg::SubmitJudgement(ranking)).await;
+		let clients = players.into_iter().map(|(client, _)| client).collect();
+		players = next_round(clients).await;
+	}
+	czars.sort();
+	assert_eq!(czars, vec!["Alice", "Bob", "Carol"]);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_czar_disconnects() {
+	let server = Server::start();
+	let mut players = start_round(&server).await;
+	let (player, hand) = &mut players[1];
+	player.send(WsMsg::SubmitAnswer(vec![hand[0].clone()])).await;
+	player.expect(|msg| matches!(msg, WsMsg::AnswerAccepted)).await;
+
+	// Without its Czar, the round is dropped, and there aren't enough players
+	// left to start another
+	let mut players = players.into_iter().map(|(client, _)| client).collect::<Vec<_>>();
+	players.remove(0).close().await;
+	for client in &mut players {
+		client.expect(|msg| matches!(msg, WsMsg::GameEnded)).await;
+		client.expect(|msg| matches!(msg, WsMsg::PlayerLeft { .. })).await;
+	}
+	assert_eq!(server.n_players().await, 2);
+	server.check_invariants().await;
+
+	// Once someone else joins, a new round starts, with full hands again
+	players.push(server.join("Dave").await);
+	next_round(players).await;
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_czar_disconnects_round_restarts() {
+	let server = Server::with_rules(Rules { min_players: 3, max_players: 4, ..Rules::default() });
+	let mut players = start_round(&server).await;
+	let mut dave = server.join("Dave").await;
+	dave.new_round().await;
+
+	let (player, hand) = &mut players[1];
+	let submitted = hand[0].clone();
+	player.send(WsMsg::SubmitAnswer(vec![submitted.clone()])).await;
+	player.expect(|msg| matches!(msg, WsMsg::AnswerAccepted)).await;
+	let submitter = player.name.clone();
+
+	// With enough players left, the round is cancelled and a new one starts,
+	// with the submitted answer back in its owner's hand
+	let mut clients = players.into_iter().map(|(client, _)| client).collect::<Vec<_>>();
+	clients.remove(0).close().await;
+	clients.push(dave);
+	let players = next_round(clients).await;
+	let (_, hand) = players.iter().find(|(client, _)| client.name == submitter).unwrap();
+	assert!(hand.contains(&submitted));
+
+	assert_eq!(server.n_players().await, 3);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_player_disconnects_while_answering() {
+	let server = Server::with_rules(Rules { min_players: 3, max_players: 4, ..Rules::default() });
+	let mut players = start_round(&server).await;
+	let mut dave = server.join("Dave").await;
+	let (_, hand) = dave.new_round().await;
+	players.push((dave, hand));
+
+	for (client, hand) in &mut players[1..3] {
+		client.send(WsMsg::SubmitAnswer(vec![hand[0].clone()])).await;
+		client.expect(|msg| matches!(msg, WsMsg::AnswerAccepted)).await;
+	}
+
+	// The last player yet to answer leaves, and judging starts without them
+	let (dave, _) = players.pop().unwrap();
+	dave.close().await;
+	let mut submissions = vec![];
+	for (client, _) in &mut players {
+		match client.expect(|msg| matches!(msg, WsMsg::ReadyToJudge { .. })).await {
+			WsMsg::ReadyToJudge { answers, .. } => {
+				assert_eq!(answers.len(), 2);
+				submissions = answers.keys().copied().collect();
+			},
+			_ => unreachable!(),
+		}
+	}
+
+	players[0].0.send(WsMsg::SubmitJudgement(submissions.into_iter().map(|id| vec![id]).collect())).await;
+	for (client, _) in &mut players {
+		match client.expect(|msg| matches!(msg, WsMsg::RoundEnded { .. })).await {
+			WsMsg::RoundEnded { ranking, .. } => assert_eq!(ranking.len(), 2),
+			_ => unreachable!(),
+		}
+	}
+
+	assert_eq!(server.n_players().await, 3);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_player_disconnects_while_judging() {
+	let server = Server::with_rules(Rules { min_players: 3, max_players: 4, ..Rules::default() });
+	let mut players = start_round(&server).await;
+	let mut dave = server.join("Dave").await;
+	let (_, hand) = dave.new_round().await;
+	players.push((dave, hand));
+	for (client, hand) in &mut players[1..] {
+		client.send(WsMsg::SubmitAnswer(vec![hand[0].clone()])).await;
+		client.expect(|msg| matches!(msg, WsMsg::AnswerAccepted)).await;
+	}
+
+	// The Czar can tell who submitted what, since cards are unique
+	let answers = match players[0].0.expect(|msg| matches!(msg, WsMsg::ReadyToJudge { .. })).await {
+		WsMsg::ReadyToJudge { answers, .. } => answers,
+		_ => unreachable!(),
+	};
+	let (dave, dave_hand) = players.pop().unwrap();
+	let dave_id = *answers.iter().find(|(_, answers)| answers[..] == dave_hand[..1]).unwrap().0;
+
+	// A player who submitted leaves: there are still enough players, but
+	// their submission can't be ranked anymore
+	dave.close().await;
+	for (client, _) in &mut players {
+		client.expect(|msg| matches!(msg, WsMsg::PlayerLeft { .. })).await;
+	}
+	let mut submissions = answers.keys().copied().collect::<Vec<_>>();
+	players[0].0.send(WsMsg::SubmitJudgement(submissions.iter().map(|id| vec![*id]).collect())).await;
+	players[0].0.expect(|msg| matches!(msg, WsMsg::JudgementRejected)).await;
+
+	submissions.retain(|id| *id != dave_id);
+	players[0].0.send(WsMsg::SubmitJudgement(submissions.iter().map(|id| vec![*id]).collect())).await;
+	for (client, _) in &mut players {
+		match client.expect(|msg| matches!(msg, WsMsg::RoundEnded { .. })).await {
+			WsMsg::RoundEnded { ranking, .. } => {
+				assert_eq!(ranking.len(), 2);
+				assert!(ranking.iter().all(|rank| rank.submission != dave_id));
+			},
+			_ => unreachable!(),
+		}
+	}
+
+	assert_eq!(server.n_players().await, 3);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test]
+async fn test_below_min_players() {
+	let server = Server::start();
+	let mut players = start_round(&server).await;
+	let submissions = submit_answers(&mut players).await;
+
+	// A player leaves during judging: the game ends, and the Czar can't judge
+	let (player, _) = players.pop().unwrap();
+	player.close().await;
+	for (client, _) in &mut players {
+		client.expect(|msg| matches!(msg, WsMsg::GameEnded)).await;
+		client.expect(|msg| matches!(msg, WsMsg::PlayerLeft { .. })).await;
+	}
+	players[0].0.send(WsMsg::SubmitJudgement(submissions.into_iter().map(|id| vec![id]).collect())).await;
+	players[0].0.send(WsMsg::GetRoundHistory).await;
+	assert_eq!(players[0].0.recv().await, WsMsg::RoundHistory(vec![]));
+
+	assert_eq!(server.n_players().await, 2);
+	server.check_invariants().await;
+	server.check_replay().await;
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_concurrent_logins() {
+	for _ in 0..5 {
+		let server = Server::start();
+
+		// More clients than the game can take log in at once, some with the same name
+		let logins = (0..12).map(|i| {
+			let server = &server;
+			async move {
+				let name = format!("Player {}", i % 4);
+				let mut client = server.connect(&name).await;
+				client.send(WsMsg::Login(name)).await;
+				let msg = client.expect(|msg| matches!(msg, WsMsg::LoginAccepted | WsMsg::LoginRejected(_))).await;
+				(client, msg == WsMsg::LoginAccepted)
+			}
+		});
+		let clients = join_all(logins).await;
+
+		let accepted = clients.iter().filter(|(_, accepted)| *accepted).map(|(client, _)| &client.name).collect::<Vec<_>>();
+		assert_eq!(accepted.len(), 3);
+		assert_eq!(accepted.iter().collect::<HashSet<_>>().len(), 3, "names should be unique: {:?}", accepted);
+		assert_eq!(server.n_players().await, 3);
+		server.check_invariants().await;
+		server.check_replay().await;
+	}
+}
@@ -0,0 +1,103 @@
+//! Actions of operators on a game, and what they see of it.
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use schema::{Answer, Prompt};
+
+use crate::Pack;
+use crate::deck::Deck;
+
+/// Action of an operator on a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action")]
+pub enum AdminAction {
+	/// Cancels the current round, if any, and starts a new one
+	NewRound,
+	EndGame,
+	Kick {
+		user: usize,
+	},
+	/// Updates the packs of cards, e.g. when the asset files changed.  Cards
+	/// in hands or in play are kept until they are discarded.
+	ReloadPacks {
+		packs: Vec<Pack>,
+	},
+}
+
+#[derive(Debug)]
+pub enum AdminError {
+	NoSuchPlayer,
+	NotEnoughPlayers,
+	NoGame,
+	EmptyDeck,
+	Failed(anyhow::Error),
+}
+
+impl fmt::Display for AdminError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			AdminError::NoSuchPlayer => write!(f, "No such player"),
+			AdminError::NotEnoughPlayers => write!(f, "Not enough players to start a round"),
+			AdminError::NoGame => write!(f, "No game is in progress"),
+			AdminError::EmptyDeck => write!(f, "Selected packs have no prompts or no answers"),
+			AdminError::Failed(e) => write!(f, "{:#}", e),
+		}
+	}
+}
+
+impl From<anyhow::Error> for AdminError {
+	fn from(e: anyhow::Error) -> Self {
+		AdminError::Failed(e)
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomDetails {
+	pub id: String,
+	pub host: Option<usize>,
+	pub paused: bool,
+	pub players: Vec<PlayerDetails>,
+	pub round: Option<RoundDetails>,
+	/// IDs of the packs the decks are made of
+	pub packs: Vec<String>,
+	pub prompts: DeckDetails,
+	pub answers: DeckDetails,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerDetails {
+	pub id: usize,
+	pub name: String,
+	pub score: u64,
+	pub hand: Vec<Answer>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoundDetails {
+	pub prompt: Prompt,
+	pub czar: usize,
+	/// Whether all answers are in, and the Czar is judging them
+	pub judging: bool,
+	/// Answers submitted by each player
+	pub submissions: BTreeMap<usize, Vec<Answer>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeckDetails {
+	pub cards: usize,
+	/// Cards that are neither in a hand nor in play
+	pub undrawn: usize,
+	pub discarded: usize,
+}
+
+impl DeckDetails {
+	pub fn of<Card: Clone + Eq>(deck: &Deck<Card>) -> DeckDetails {
+		DeckDetails {
+			cards: deck.n_cards(),
+			undrawn: deck.n_undrawn(),
+			discarded: deck.n_discarded(),
+		}
+	}
+}
@@ -0,0 +1,641 @@
+//! Decks of cards, which are drawn in a random order and discarded.
+//!
+//! Drawn cards come with a [`CardHandle`] that identifies the copy that was
+//! drawn, so that decks can have several copies of the same card.  Cards are
+//! discarded by handle, in constant time.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::Extend;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::mem;
+
+/// Source of the IDs of decks, to tell which deck a handle belongs to
+static NEXT_DECK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Identifies a drawn card.  Handles are only valid until the card is
+/// discarded, or the deck reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CardHandle {
+	deck: usize,
+	index: usize,
+	/// Number of times the card was drawn, to tell draws of the same card apart
+	generation: u32,
+}
+
+/// A card drawn from a deck, with the handle to discard it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drawn<Card> {
+	pub handle: CardHandle,
+	pub card: Card,
+}
+
+/// The cards of drawn cards
+pub fn cards<Card: Clone>(drawn: &[Drawn<Card>]) -> Vec<Card> {
+	drawn.iter().map(|drawn| drawn.card.clone()).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckError {
+	/// There aren't enough cards left to draw, even after reshuffling the
+	/// discarded ones
+	Exhausted,
+	/// The handle is from another deck
+	NotInDeck,
+	/// The card was already discarded, or the deck reset since it was drawn
+	NotDrawn,
+}
+
+impl fmt::Display for DeckError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DeckError::Exhausted => write!(f, "Not enough cards left to draw"),
+			DeckError::NotInDeck => write!(f, "Card isn't from this deck"),
+			DeckError::NotDrawn => write!(f, "Card isn't drawn"),
+		}
+	}
+}
+
+impl std::error::Error for DeckError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CardState {
+	/// Remaining or discarded
+	Undrawn,
+	Drawn,
+	/// Removed from the deck while drawn, to retire once discarded
+	Retiring,
+	/// Removed from the deck, and never drawn again
+	Retired,
+}
+
+struct Slot<Card> {
+	card: Card,
+	state: CardState,
+	generation: u32,
+}
+
+pub struct Deck<Card> {
+	id: usize,
+	cards: Vec<Slot<Card>>,
+	remaining: Vec<usize>,
+	discarded: Vec<usize>,
+	n_retired: usize,
+	n_reshuffles: u64,
+	rng: StdRng,
+}
+
+impl<Card> Default for Deck<Card> {
+	fn default() -> Deck<Card> {
+		Deck::with_rng(StdRng::from_entropy())
+	}
+}
+
+impl<Card> Deck<Card> {
+	/// Creates an empty deck, shuffled with the given RNG.  Two decks with
+	/// identically seeded RNGs will be shuffled identically.
+	pub fn with_rng(rng: StdRng) -> Deck<Card> {
+		Deck {
+			id: NEXT_DECK_ID.fetch_add(1, Ordering::Relaxed),
+			cards: vec![],
+			remaining: vec![],
+			discarded: vec![],
+			n_retired: 0,
+			n_reshuffles: 0,
+			rng,
+		}
+	}
+}
+
+impl<Card: Clone + Eq> Extend<Card> for Deck<Card> {
+	fn extend<T: IntoIterator<Item=Card>>(&mut self, iter: T) {
+		for elem in iter {
+			self.add(elem);
+		}
+	}
+}
+
+fn reshuffle(remaining: &mut Vec<usize>, discarded: &mut Vec<usize>, rng: &mut StdRng) {
+	*remaining = mem::take(discarded);
+	// Sort first, so that the order in which cards were discarded doesn't matter
+	remaining.sort_unstable();
+	remaining.shuffle(rng);
+}
+
+impl<Card: Clone + Eq> Deck<Card> {
+	pub fn add(&mut self, card: Card) {
+		self.cards.push(Slot { card, state: CardState::Undrawn, generation: 0 });
+		self.discarded.push(self.cards.len()-1);
+	}
+
+	/// Draws `n_cards` cards, or none if there aren't enough left
+	pub fn draw(&mut self, n_cards: usize) -> Result<Vec<Drawn<Card>>, DeckError> {
+		if n_cards > self.n_undrawn() {
+			return Err(DeckError::Exhausted);
+		}
+		(0..n_cards).map(|_| self.draw_once()).collect()
+	}
+
+	pub fn draw_once(&mut self) -> Result<Drawn<Card>, DeckError> {
+		let index = match self.remaining.pop() {
+			Some(index) => index,
+			None => {
+				reshuffle(&mut self.remaining, &mut self.discarded, &mut self.rng);
+				self.n_reshuffles += 1;
+				self.remaining.pop().ok_or(DeckError::Exhausted)?
+			}
+		};
+		let slot = &mut self.cards[index];
+		slot.state = CardState::Drawn;
+		slot.generation += 1;
+		Ok(Drawn {
+			handle: CardHandle { deck: self.id, index, generation: slot.generation },
+			card: slot.card.clone(),
+		})
+	}
+
+	/// Returns a drawn card to the deck
+	pub fn discard(&mut self, handle: CardHandle) -> Result<(), DeckError> {
+		if handle.deck != self.id || handle.index >= self.cards.len() {
+			return Err(DeckError::NotInDeck);
+		}
+		let slot = &mut self.cards[handle.index];
+		if slot.generation != handle.generation {
+			return Err(DeckError::NotDrawn);
+		}
+		match slot.state {
+			CardState::Drawn => {
+				slot.state = CardState::Undrawn;
+				self.discarded.push(handle.index);
+			},
+			CardState::Retiring => {
+				slot.state = CardState::Retired;
+				self.n_retired += 1;
+			},
+			_ => return Err(DeckError::NotDrawn),
+		}
+		Ok(())
+	}
+
+	/// Discards all the cards, stopping at the first that can't be discarded
+	pub fn discard_all<'a>(&mut self, drawn: impl IntoIterator<Item=&'a Drawn<Card>>) -> Result<(), DeckError>
+	where Card: 'a {
+		drawn.into_iter().try_for_each(|drawn| self.discard(drawn.handle))
+	}
+
+	/// Returns all cards to the deck.  Handles of drawn cards become invalid.
+	pub fn reset(&mut self) {
+		self.remaining.clear();
+		self.discarded.clear();
+		for (i, slot) in self.cards.iter_mut().enumerate() {
+			match slot.state {
+				CardState::Retiring => {
+					slot.state = CardState::Retired;
+					self.n_retired += 1;
+				},
+				CardState::Retired => {},
+				_ => {
+					slot.state = CardState::Undrawn;
+					self.discarded.push(i);
+				},
+			}
+			slot.generation += 1;
+		}
+	}
+
+	/// All cards ever added to the deck, including retired ones
+	pub fn cards(&self) -> impl Iterator<Item=&Card> {
+		self.cards.iter().map(|slot| &slot.card)
+	}
+
+	/// Number of cards in the deck, drawn or not, excluding retired ones
+	pub fn n_cards(&self) -> usize {
+		self.cards.len() - self.n_retired
+	}
+
+	/// Whether a copy of the card is in the deck, and isn't being retired
+	pub fn contains(&self, card: &Card) -> bool {
+		self.cards.iter()
+			.any(|slot| slot.card == *card && !matches!(slot.state, CardState::Retiring | CardState::Retired))
+	}
+
+	/// Number of cards that aren't drawn, i.e., that are either remaining or
+	/// discarded.
+	pub fn n_undrawn(&self) -> usize {
+		self.remaining.len() + self.discarded.len()
+	}
+
+	pub fn n_discarded(&self) -> usize {
+		self.discarded.len()
+	}
+
+	/// Number of times the discarded cards were shuffled back into the deck
+	pub fn n_reshuffles(&self) -> u64 {
+		self.n_reshuffles
+	}
+}
+
+impl<Card: Clone + Eq + Hash> Deck<Card> {
+	/// Updates the deck to contain `cards`, without disturbing the ones that
+	/// are drawn.  New cards are shuffled in with the discarded ones, and
+	/// cards that are gone are retired: right away if they aren't drawn,
+	/// otherwise once they are discarded.
+	pub fn merge(&mut self, cards: Vec<Card>) {
+		let mut wanted = HashMap::<Card, usize>::new();
+		for card in cards.iter() {
+			*wanted.entry(card.clone()).or_default() += 1;
+		}
+
+		// Keep as many copies of each card as wanted, starting with those
+		// that aren't being retired
+		let mut kept = (0..self.cards.len())
+			.filter(|&i| self.cards[i].state != CardState::Retired)
+			.collect::<Vec<_>>();
+		kept.sort_by_key(|&i| self.cards[i].state == CardState::Retiring);
+		for i in kept {
+			let slot = &mut self.cards[i];
+			match wanted.get_mut(&slot.card) {
+				Some(n) if *n > 0 => {
+					*n -= 1;
+					if slot.state == CardState::Retiring {
+						slot.state = CardState::Drawn;
+					}
+				},
+				_ => match slot.state {
+					CardState::Drawn | CardState::Retiring => slot.state = CardState::Retiring,
+					_ => {
+						self.remaining.retain(|&j| j != i);
+						self.discarded.retain(|&j| j != i);
+						slot.state = CardState::Retired;
+						self.n_retired += 1;
+					},
+				},
+			}
+		}
+
+		// Add the remaining cards, in the order they were given
+		for card in cards {
+			if let Some(n) = wanted.get_mut(&card) {
+				if *n > 0 {
+					*n -= 1;
+					self.add(card);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// Note this useful idiom: importing names from outer (for mod tests) scope.
+	use std::collections::HashSet;
+
+	use super::*;
+
+	#[test]
+	fn test_deck_draw_no_repeat() {
+		for _ in 0..100 {
+			let mut deck = Deck::<i32>::default();
+			for i in 0..12 {
+				deck.add(i);
+			}
+
+			let mut set = HashSet::new();
+			for _ in 0..3 {
+				set.extend(cards(&deck.draw(4).unwrap()));
+			}
+			
+			assert_eq!(set, deck.cards().copied().collect());
+		}
+	}
+
+	#[test]
+	fn test_deck_wont_redraw() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(2);
+		assert_ne!(deck.draw_once().unwrap().card, deck.draw_once().unwrap().card);
+	}
+
+	#[test]
+	fn test_deck_wont_draw_discarded() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(2);
+		let drawn = deck.draw_once().unwrap();
+		deck.discard(drawn.handle).unwrap();
+		assert_ne!(deck.draw_once().unwrap().card, drawn.card);
+	}
+
+	#[test]
+	fn test_deck_reuse_discarded() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(2);
+		deck.draw_once().unwrap();
+		let drawn = deck.draw_once().unwrap();
+		deck.discard(drawn.handle).unwrap();
+		assert_eq!(deck.draw_once().unwrap().card, drawn.card);
+	}
+
+	#[test]
+	fn test_deck_reset() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(2);
+		deck.draw_once().unwrap();
+		deck.reset();
+		let mut drawn = cards(&deck.draw(2).unwrap());
+		drawn.sort();
+		assert_eq!(drawn, vec![1, 2]);
+	}
+
+	#[test]
+	fn test_deck_reset_discard() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(2);
+		let card = deck.draw_once().unwrap();
+		deck.discard(card.handle).unwrap();
+		deck.reset();
+		let drawn = deck.draw(2).unwrap();
+		deck.discard_all(&drawn).unwrap();  // This used to fail
+	}
+
+	#[test]
+	fn test_deck_same_seed_same_draws() {
+		let new_deck = || {
+			let mut deck = Deck::<i32>::with_rng(StdRng::seed_from_u64(42));
+			deck.extend(0..50);
+			deck
+		};
+		let mut deck1 = new_deck();
+		let mut deck2 = new_deck();
+
+		let drawn1 = deck1.draw(30).unwrap();
+		let drawn2 = deck2.draw(30).unwrap();
+		assert_eq!(cards(&drawn1), cards(&drawn2));
+
+		// Discard order doesn't affect the next shuffle
+		deck1.discard_all(&drawn1).unwrap();
+		deck2.discard_all(drawn2.iter().rev()).unwrap();
+		assert_eq!(cards(&deck1.draw(50).unwrap()), cards(&deck2.draw(50).unwrap()));
+	}
+
+	#[test]
+	fn test_deck_discard_duplicates() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(1);
+		let drawn = deck.draw(2).unwrap();
+		assert_ne!(drawn[0].handle, drawn[1].handle);
+		deck.discard_all(&drawn).unwrap();
+		assert_eq!(cards(&deck.draw(2).unwrap()), vec![1, 1]);
+	}
+
+	#[test]
+	fn test_deck_merge_keeps_drawn_cards() {
+		let mut deck = Deck::<i32>::default();
+		deck.extend(vec![1, 2, 3]);
+		let drawn = deck.draw(3).unwrap();
+		deck.merge(vec![2, 3, 4]);
+		assert_eq!(deck.n_cards(), 4);
+		assert_eq!(deck.n_undrawn(), 1);
+		assert!(!deck.contains(&1));
+
+		// Discarding the removed card retires it
+		deck.discard_all(&drawn).unwrap();
+		assert_eq!(deck.n_cards(), 3);
+		let mut drawn = cards(&deck.draw(3).unwrap());
+		drawn.sort();
+		assert_eq!(drawn, vec![2, 3, 4]);
+	}
+
+	#[test]
+	fn test_deck_merge_retires_undrawn_cards() {
+		let mut deck = Deck::<i32>::default();
+		deck.extend(vec![1, 1, 2]);
+		deck.merge(vec![1, 2, 2]);
+		let mut drawn = cards(&deck.draw(3).unwrap());
+		drawn.sort();
+		assert_eq!(drawn, vec![1, 2, 2]);
+		assert_eq!(deck.n_cards(), 3);
+	}
+
+	#[test]
+	fn test_deck_merge_restores_retiring_cards() {
+		let mut deck = Deck::<i32>::default();
+		deck.extend(vec![1, 2]);
+		let drawn = deck.draw(2).unwrap();
+		deck.merge(vec![2]);
+		deck.merge(vec![1, 2]);
+		deck.discard_all(&drawn).unwrap();
+		assert_eq!(deck.n_cards(), 2);
+		assert_eq!(deck.n_undrawn(), 2);
+	}
+
+	#[test]
+	fn test_deck_discard_twice() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		deck.add(1);
+		let drawn = deck.draw(2).unwrap();
+		deck.discard(drawn[0].handle).unwrap();
+		assert_eq!(deck.discard(drawn[0].handle), Err(DeckError::NotDrawn));
+
+		// The handle doesn't apply to the card once it's drawn again
+		deck.draw(1).unwrap();
+		assert_eq!(deck.discard(drawn[0].handle), Err(DeckError::NotDrawn));
+		deck.discard(drawn[1].handle).unwrap();
+	}
+
+	#[test]
+	fn test_deck_discard_after_reset() {
+		let mut deck = Deck::<i32>::default();
+		deck.add(1);
+		let drawn = deck.draw_once().unwrap();
+		deck.reset();
+		assert_eq!(deck.discard(drawn.handle), Err(DeckError::NotDrawn));
+		assert_eq!(deck.n_undrawn(), 1);
+	}
+
+	#[test]
+	fn test_deck_discard_foreign_card() {
+		let mut deck1 = Deck::<i32>::default();
+		let mut deck2 = Deck::<i32>::default();
+		deck1.add(1);
+		deck2.add(1);
+		let drawn = deck1.draw_once().unwrap();
+		deck2.draw_once().unwrap();
+		assert_eq!(deck2.discard(drawn.handle), Err(DeckError::NotInDeck));
+	}
+
+	#[test]
+	fn test_deck_exhausted() {
+		let mut deck = Deck::<i32>::default();
+		assert_eq!(deck.draw_once(), Err(DeckError::Exhausted));
+		deck.extend(vec![1, 2, 3]);
+		let drawn = deck.draw(2).unwrap();
+		assert_eq!(deck.draw(2), Err(DeckError::Exhausted));
+		assert_eq!(deck.n_undrawn(), 1, "nothing is drawn if there aren't enough cards");
+		deck.discard_all(&drawn).unwrap();
+		assert_eq!(deck.draw(3).unwrap().len(), 3);
+	}
+}
+
+/// Random sequences of operations on a deck, checked against a model of it
+#[cfg(test)]
+mod proptests {
+	use proptest::collection::vec;
+	use proptest::prelude::*;
+	use std::collections::HashSet;
+
+	use super::*;
+
+	#[derive(Debug, Clone)]
+	enum Op {
+		/// Few values, so that decks have duplicates
+		Add(u8),
+		Draw(usize),
+		DrawOnce,
+		/// Discards one of the drawn cards
+		Discard(usize),
+		/// Discards one of the cards that were drawn, but aren't anymore
+		DiscardStale(usize),
+		Reset,
+	}
+
+	fn op() -> impl Strategy<Value = Op> {
+		prop_oneof![
+			4 => (0..4u8).prop_map(Op::Add),
+			3 => (0..6usize).prop_map(Op::Draw),
+			3 => Just(Op::DrawOnce),
+			4 => any::<usize>().prop_map(Op::Discard),
+			1 => any::<usize>().prop_map(Op::DiscardStale),
+			1 => Just(Op::Reset),
+		]
+	}
+
+	#[derive(Default)]
+	struct Model {
+		cards: Vec<u8>,
+		/// Handles of the drawn cards
+		drawn: Vec<CardHandle>,
+		/// Handles that were valid once, but aren't anymore
+		stale: Vec<CardHandle>,
+		/// Cards drawn since the last reshuffle
+		since_reshuffle: HashSet<usize>,
+	}
+
+	impl Model {
+		/// Checks cards drawn from the deck, `n_before_reshuffle` of which
+		/// were drawn before it reshuffled
+		fn drawn(&mut self, drawn: &[Drawn<u8>], n_before_reshuffle: usize) -> Result<(), TestCaseError> {
+			for (i, drawn) in drawn.iter().enumerate() {
+				if i == n_before_reshuffle {
+					self.since_reshuffle.clear();
+				}
+				prop_assert_eq!(drawn.card, self.cards[drawn.handle.index]);
+				prop_assert!(self.since_reshuffle.insert(drawn.handle.index), "card {} was drawn twice before reshuffling", drawn.handle.index);
+				self.drawn.push(drawn.handle);
+			}
+			Ok(())
+		}
+
+		/// Checks that every card is either remaining, discarded or drawn
+		fn check(&self, deck: &Deck<u8>) -> Result<(), TestCaseError> {
+			prop_assert_eq!(deck.cards().copied().collect::<Vec<_>>(), self.cards.clone());
+			let mut seen = vec![0; self.cards.len()];
+			for &i in deck.remaining.iter().chain(&deck.discarded) {
+				seen[i] += 1;
+				prop_assert_eq!(deck.cards[i].state, CardState::Undrawn);
+			}
+			for handle in &self.drawn {
+				seen[handle.index] += 1;
+				prop_assert_eq!(deck.cards[handle.index].state, CardState::Drawn);
+			}
+			prop_assert!(seen.iter().all(|&n| n == 1), "cards are lost or duplicated: {:?}", seen);
+			prop_assert_eq!(deck.n_cards(), self.cards.len());
+			prop_assert_eq!(deck.n_undrawn(), self.cards.len() - self.drawn.len());
+			Ok(())
+		}
+	}
+
+	proptest! {
+		#[test]
+		fn test_deck_model(seed: u64, ops in vec(op(), 0..200)) {
+			let mut deck = Deck::<u8>::with_rng(StdRng::seed_from_u64(seed));
+			let mut model = Model::default();
+
+			for op in ops {
+				match op {
+					Op::Add(card) => {
+						deck.add(card);
+						model.cards.push(card);
+					},
+					Op::Draw(n) => {
+						let n_before_reshuffle = deck.remaining.len();
+						match deck.draw(n) {
+							Ok(drawn) => {
+								prop_assert_eq!(drawn.len(), n);
+								model.drawn(&drawn, n_before_reshuffle)?;
+							},
+							Err(e) => {
+								prop_assert_eq!(e, DeckError::Exhausted);
+								prop_assert!(n > model.cards.len() - model.drawn.len());
+							},
+						}
+					},
+					Op::DrawOnce => {
+						let n_before_reshuffle = deck.remaining.len();
+						match deck.draw_once() {
+							Ok(drawn) => model.drawn(&[drawn], n_before_reshuffle)?,
+							Err(e) => {
+								prop_assert_eq!(e, DeckError::Exhausted);
+								prop_assert_eq!(model.cards.len(), model.drawn.len());
+							},
+						}
+					},
+					Op::Discard(i) if !model.drawn.is_empty() => {
+						let handle = model.drawn.swap_remove(i % model.drawn.len());
+						prop_assert_eq!(deck.discard(handle), Ok(()));
+						model.stale.push(handle);
+					},
+					Op::DiscardStale(i) if !model.stale.is_empty() => {
+						let handle = model.stale[i % model.stale.len()];
+						prop_assert_eq!(deck.discard(handle), Err(DeckError::NotDrawn));
+					},
+					Op::Discard(_) | Op::DiscardStale(_) => {},
+					Op::Reset => {
+						deck.reset();
+						model.stale.append(&mut model.drawn);
+						prop_assert!(deck.remaining.is_empty());
+						prop_assert_eq!(deck.n_undrawn(), model.cards.len());
+					},
+				}
+				model.check(&deck)?;
+			}
+		}
+
+		#[test]
+		fn test_deck_same_seed_same_draws(seed: u64, n_cards in 1..50usize, n_draws in 1..100usize) {
+			let new_deck = || {
+				let mut deck = Deck::<usize>::with_rng(StdRng::seed_from_u64(seed));
+				deck.extend(0..n_cards);
+				deck
+			};
+			let (mut deck1, mut deck2) = (new_deck(), new_deck());
+			for _ in 0..n_draws {
+				let drawn1 = deck1.draw_once().unwrap();
+				let drawn2 = deck2.draw_once().unwrap();
+				prop_assert_eq!(drawn1.card, drawn2.card);
+				deck1.discard(drawn1.handle).unwrap();
+				deck2.discard(drawn2.handle).unwrap();
+			}
+		}
+	}
+}
@@ -0,0 +1,70 @@
+use serde::{Serialize, Deserialize};
+
+use schema::{Message as WsMsg, Prompt, Answer, ExhaustionPolicy};
+
+use crate::{Pack, Rules};
+use crate::admin::AdminAction;
+
+/// Something that happened in a game: either an action of a client, or a
+/// decision taken by the server in response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Event {
+	GameCreated {
+		seed: u64,
+		packs: Vec<Pack>,
+		rules: Rules,
+	},
+
+	// Client actions
+	Connected {
+		user: usize,
+	},
+	Received {
+		user: usize,
+		message: WsMsg,
+	},
+	Disconnected {
+		user: usize,
+	},
+	/// Action of an operator, through the admin API
+	Admin {
+		action: AdminAction,
+	},
+
+	// Server decisions
+	PlayerJoined {
+		user: usize,
+		name: String,
+	},
+	PlayerRemoved {
+		user: usize,
+	},
+	RoundStarted {
+		czar: usize,
+		prompt: Prompt,
+	},
+	AnswerAccepted {
+		user: usize,
+		answers: Vec<Answer>,
+	},
+	JudgingStarted,
+	/// Submission, position and points of each ranked submission
+	RoundEnded {
+		ranking: Vec<(usize, usize, u64)>,
+	},
+	RoundCancelled,
+	GameEnded,
+	/// There weren't enough answers left to deal full hands
+	CardsExhausted {
+		policy: ExhaustionPolicy,
+	},
+	AnswerSaved {
+		answer: Answer,
+	},
+	Paused,
+	Resumed,
+	PacksSelected {
+		packs: Vec<String>,
+	},
+}
@@ -0,0 +1,1654 @@
+use anyhow::{Result, bail, ensure};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque, hash_map};
+use std::time::Duration;
+use std::mem;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use tracing::{debug, error, info, warn};
+
+use schema::{
+	Message as WsMsg,
+	Role,
+	Prompt,
+	Answer,
+	RankedAnswers,
+	RoundRecord,
+	SubmissionRecord,
+	ChatMessage,
+	LoginRejectedReason,
+	ExhaustionPolicy,
+};
+
+use crate::admin::{AdminAction, AdminError, DeckDetails, PlayerDetails, RoomDetails, RoundDetails};
+use crate::Pack;
+use crate::util::sanitize_text;
+use crate::deck::{self, Deck, Drawn};
+use crate::event::Event;
+use crate::rate_limit::RateLimiter;
+use crate::reactions::Reactions;
+use crate::scoring::score_ranking;
+
+/// Identifies a client of the game, whether they joined as a player or not
+pub type PlayerId = usize;
+
+/// Something a client does to the game
+#[derive(Debug, Clone)]
+pub enum Action {
+	Connect,
+	Message(WsMsg),
+	Disconnect,
+}
+
+/// Something the game does in response to an action, for the server to carry
+/// out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+	/// Sends a message to a client
+	Send {
+		to: PlayerId,
+		message: WsMsg,
+	},
+	/// Records an event in the game's log
+	Log(Event),
+	/// Reports an invalid query from a client
+	Rejected {
+		query: &'static str,
+		reason: &'static str,
+	},
+}
+
+/// Rules that can differ from one game to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+	/// Players needed to play rounds
+	pub min_players: usize,
+	/// Players who log in beyond this are turned away
+	pub max_players: usize,
+	/// Points awarded to the submissions ranked by the Czar, from best to
+	/// worst
+	pub points_per_rank: Vec<u64>,
+	/// What to do when there aren't enough answers left to deal full hands
+	pub exhaustion_policy: ExhaustionPolicy,
+}
+
+impl Default for Rules {
+	fn default() -> Self {
+		Rules {
+			min_players: MIN_N_PLAYERS,
+			max_players: MAX_N_PLAYERS,
+			points_per_rank: POINTS_PER_RANK.to_vec(),
+			exhaustion_policy: ExhaustionPolicy::RecycleHands,
+		}
+	}
+}
+
+/// Effects of the action being applied, in order
+#[derive(Default)]
+struct Effects(Vec<Effect>);
+
+impl Effects {
+	fn send(&mut self, to: PlayerId, message: WsMsg) {
+		self.0.push(Effect::Send { to, message });
+	}
+
+	fn record(&mut self, event: Event) {
+		self.0.push(Effect::Log(event));
+	}
+
+	fn reject(&mut self, query: &'static str, reason: &'static str) {
+		self.0.push(Effect::Rejected { query, reason });
+	}
+
+	fn take(&mut self) -> Vec<Effect> {
+		mem::take(&mut self.0)
+	}
+}
+
+/// State of a game, changed only by the actions applied to it.  The game
+/// doesn't do any I/O: what it does in response to actions is returned as
+/// effects.
+pub struct Game {
+	rules: Rules,
+	prompts: Deck<Prompt>,
+	answers: Deck<Answer>,
+	round: Option<Round>,
+	/// Clients connected to the game, whether they joined or not
+	clients: HashSet<PlayerId>,
+	/// Players, ordered by ID so that iterating over them is deterministic
+	players: BTreeMap<usize, Player>,
+	/// Votes to save the blank cards written during the last round
+	save_votes: HashMap<Answer, HashSet<usize>>,
+	/// Written answers saved to the deck, kept when the decks are reloaded
+	saved_answers: Vec<Answer>,
+	/// Packs of cards that can be played, sorted by ID
+	packs: Vec<Pack>,
+	/// IDs of the packs the decks are made of
+	selected_packs: Vec<String>,
+	/// Player who can kick others.  Without a host, players vote to kick.
+	host: Option<usize>,
+	/// Votes to kick each player
+	kick_votes: HashMap<usize, HashSet<usize>>,
+	/// While paused, answers and judgements aren't accepted
+	paused: bool,
+	/// Last chat messages, sent to players when they join
+	chat_history: VecDeque<ChatMessage>,
+	/// Reactions to the winning submissions of the last round
+	winner_reactions: Reactions,
+	/// Authors of the winning submissions of the last round, by submission ID
+	winners: HashMap<usize, usize>,
+	/// Rounds played since the first player joined
+	history: Vec<RoundRecord>,
+	effects: Effects,
+	/// Names and clients banned from the game, and when their ban ends
+	banned_names: HashMap<String, Duration>,
+	banned_clients: HashMap<usize, Duration>,
+	/// Time of the action being applied
+	now: Duration,
+	/// Shuffles the IDs of submissions, so that they can't be traced back to
+	/// their authors
+	rng: StdRng,
+}
+
+static N_CARDS_IN_HAND: usize = 4;
+static MIN_N_PLAYERS: usize = 3;
+static MAX_N_PLAYERS: usize = 3;
+static MAX_WRITTEN_ANSWER_LENGTH: usize = 100;
+static POINTS_PER_RANK: &[u64] = &[3, 2, 1];
+/// Whether the first player to join becomes host.  If the host leaves, the
+/// game stays without a host until all players have left.
+static FIRST_PLAYER_IS_HOST: bool = true;
+static KICK_BAN_DURATION: Duration = Duration::from_secs(5 * 60);
+static CHAT_HISTORY_LENGTH: usize = 50;
+static MAX_CHAT_MESSAGE_LENGTH: usize = 300;
+/// Players can send this many chat messages...
+static CHAT_RATE_LIMIT: usize = 5;
+/// ... during this long
+static CHAT_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+impl Game {
+	/// Creates a game whose decks are shuffled deterministically from `seed`.
+	/// Given the same seed and cards, the same actions of clients lead to the
+	/// same game, which can be replayed from its log.  All packs are selected
+	/// at first.
+	pub fn new(seed: u64, packs: Vec<Pack>) -> Game {
+		Game::with_rules(seed, packs, Rules::default())
+	}
+
+	/// Creates a game like `new`, played with the given rules
+	pub fn with_rules(seed: u64, packs: Vec<Pack>, rules: Rules) -> Game {
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut game = Game {
+			rules,
+			prompts: Deck::with_rng(StdRng::seed_from_u64(rng.gen())),
+			answers: Deck::with_rng(StdRng::seed_from_u64(rng.gen())),
+			round: None,
+			clients: HashSet::new(),
+			players: BTreeMap::new(),
+			save_votes: HashMap::new(),
+			saved_answers: vec![],
+			selected_packs: packs.iter().map(|pack| pack.info.id.clone()).collect(),
+			packs,
+			host: None,
+			kick_votes: HashMap::new(),
+			paused: false,
+			chat_history: VecDeque::new(),
+			winner_reactions: Reactions::default(),
+			winners: HashMap::new(),
+			history: vec![],
+			effects: Effects::default(),
+			banned_names: HashMap::new(),
+			banned_clients: HashMap::new(),
+			now: Duration::default(),
+			rng: StdRng::seed_from_u64(rng.gen()),
+		};
+		let (prompts, answers) = game.selected_cards(&game.selected_packs);
+		game.prompts.extend(prompts);
+		game.answers.extend(answers);
+		game
+	}
+
+	/// Applies an action of a client, returning what the game did in
+	/// response.  Every action is logged first, so that the game can be
+	/// replayed.
+	///
+	/// `now` is the time since the game was created, and mustn't go backwards.
+	/// The game has no clock of its own: replaying the same actions at the same
+	/// times leads to the same game, e.g., bans expire at the same step.
+	pub fn apply(&mut self, now: Duration, user_id: PlayerId, action: Action) -> Vec<Effect> {
+		self.now = now;
+		match action {
+			Action::Connect => {
+				self.effects.record(Event::Connected { user: user_id });
+				self.clients.insert(user_id);
+			},
+			Action::Message(msg) => {
+				self.effects.record(Event::Received { user: user_id, message: msg.clone() });
+				if let Err(e) = process_message(self, user_id, msg) {
+					error!(error = %format!("{:#}", e), "error while processing message");
+				}
+			},
+			Action::Disconnect => {
+				self.effects.record(Event::Disconnected { user: user_id });
+				user_disconnected(self, user_id);
+			},
+		}
+		self.effects.take()
+	}
+
+	pub fn history(&self) -> &[RoundRecord] {
+		&self.history
+	}
+
+	pub fn n_players(&self) -> usize {
+		self.players.len()
+	}
+
+	/// Number of times the decks ran out and were reshuffled
+	pub fn n_reshuffles(&self) -> u64 {
+		self.prompts.n_reshuffles() + self.answers.n_reshuffles()
+	}
+
+	/// Checks that the state of the game is consistent, e.g., that no card was
+	/// lost or duplicated.
+	pub fn check_invariants(&self) -> Result<()> {
+		let in_hands = self.players.values().map(|player| player.hand.len()).sum::<usize>();
+		let submitted = self.round.iter().flat_map(|round| round.answers.values()).map(Vec::len).sum::<usize>();
+		ensure!(
+			self.answers.n_undrawn() + in_hands + submitted == self.answers.n_cards(),
+			"{} answers in the deck, {} in hands and {} submitted, but there are {} answers",
+			self.answers.n_undrawn(), in_hands, submitted, self.answers.n_cards()
+		);
+
+		let in_play = self.round.iter().count();
+		ensure!(
+			self.prompts.n_undrawn() + in_play == self.prompts.n_cards(),
+			"{} prompts in the deck and {} in play, but there are {} prompts",
+			self.prompts.n_undrawn(), in_play, self.prompts.n_cards()
+		);
+
+		if let Some(round) = &self.round {
+			ensure!(self.players.contains_key(&round.czar), "Czar #{} isn't playing", round.czar);
+			ensure!(!round.answers.contains_key(&round.czar), "Czar #{} submitted answers", round.czar);
+			for id in round.answers.keys() {
+				ensure!(self.players.contains_key(id), "Player #{} submitted answers but isn't playing", id);
+			}
+			if round.state == RoundState::Judging {
+				ensure!(round.answers.len() + 1 == self.players.len(), "Judging before all players answered");
+			}
+		}
+
+		if let Some(host) = self.host {
+			ensure!(self.players.contains_key(&host), "Host #{} isn't playing", host);
+		}
+
+		for id in self.players.keys() {
+			ensure!(self.clients.contains(id), "Player #{} has no client", id);
+		}
+
+		Ok(())
+	}
+
+	/// Applies an action of an operator at time `now`.  Like client messages,
+	/// the action is logged, so that the game can still be replayed.  Rejected
+	/// actions change nothing, and aren't logged.
+	pub fn apply_admin(&mut self, now: Duration, action: AdminAction) -> Result<Vec<Effect>, AdminError> {
+		self.now = now;
+		self.effects.record(Event::Admin { action: action.clone() });
+		match self.admin(action) {
+			Ok(()) => Ok(self.effects.take()),
+			Err(e) => {
+				self.effects.take();
+				Err(e)
+			},
+		}
+	}
+
+	fn admin(&mut self, action: AdminAction) -> Result<(), AdminError> {
+		match action {
+			AdminAction::NewRound => {
+				if self.players.len() < self.rules.min_players {
+					return Err(AdminError::NotEnoughPlayers);
+				}
+				info!("admin forced a new round");
+				let next_czar = self.cancel_round().map_or(0, |round| round.czar + 1);
+				self.start_round(next_czar)?;
+			},
+			AdminAction::EndGame => {
+				if self.round.is_none() {
+					return Err(AdminError::NoGame);
+				}
+				info!("admin ended the game");
+				self.end_game();
+			},
+			AdminAction::Kick { user } => {
+				if !self.players.contains_key(&user) {
+					return Err(AdminError::NoSuchPlayer);
+				}
+				self.kick_player(user);
+			},
+			AdminAction::ReloadPacks { packs } => {
+				info!(packs = packs.len(), "reloading the packs");
+				let old_packs = mem::replace(&mut self.packs, packs);
+				// Keep the selected packs that still exist
+				let selected = self.selected_packs.iter()
+					.filter(|id| self.packs.iter().any(|pack| pack.info.id == **id))
+					.cloned()
+					.collect();
+				if let Err(e) = self.select_packs(selected) {
+					self.packs = old_packs;
+					return Err(e);
+				}
+				self.broadcast_to_players(&self.pack_list());
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Cards of the given packs, with the answers saved by players
+	fn selected_cards(&self, selected: &[String]) -> (Vec<Prompt>, Vec<Answer>) {
+		let packs = self.packs.iter().filter(|pack| selected.contains(&pack.info.id));
+		let prompts = packs.clone().flat_map(|pack| pack.prompts.iter().cloned()).collect();
+		let mut answers = packs.flat_map(|pack| pack.answers.iter().cloned()).collect::<Vec<_>>();
+		let saved = self.saved_answers.iter().filter(|card| !answers.contains(card)).cloned().collect::<Vec<_>>();
+		answers.extend(saved);
+		(prompts, answers)
+	}
+
+	/// Makes the decks out of the given packs.  Cards in hands or in play are
+	/// kept until they are discarded.
+	fn select_packs(&mut self, selected: Vec<String>) -> Result<(), AdminError> {
+		let (prompts, answers) = self.selected_cards(&selected);
+		if prompts.is_empty() || answers.is_empty() {
+			return Err(AdminError::EmptyDeck);
+		}
+
+		info!(packs = ?selected, prompts = prompts.len(), answers = answers.len(), "selecting packs");
+		self.effects.record(Event::PacksSelected { packs: selected.clone() });
+		self.selected_packs = selected;
+		self.prompts.merge(prompts);
+		self.answers.merge(answers);
+		Ok(())
+	}
+
+	fn pack_list(&self) -> WsMsg {
+		WsMsg::PackList {
+			packs: self.packs.iter().map(|pack| pack.info.clone()).collect(),
+			selected: self.selected_packs.clone(),
+		}
+	}
+
+	/// Describes the state of the game, for operators
+	pub fn details(&self, id: &str) -> RoomDetails {
+		RoomDetails {
+			id: id.to_owned(),
+			host: self.host,
+			paused: self.paused,
+			players: self.players.iter().map(|(id, player)| PlayerDetails {
+				id: *id,
+				name: player.name.clone(),
+				score: player.score,
+				hand: deck::cards(&player.hand),
+			}).collect(),
+			round: self.round.as_ref().map(|round| RoundDetails {
+				prompt: round.prompt.card.clone(),
+				czar: round.czar,
+				judging: round.state == RoundState::Judging,
+				submissions: round.answers.iter().map(|(id, answers)| (*id, deck::cards(answers))).collect(),
+			}),
+			packs: self.selected_packs.clone(),
+			prompts: DeckDetails::of(&self.prompts),
+			answers: DeckDetails::of(&self.answers),
+		}
+	}
+
+	/// Deals answers to the given players until their hands are full.  If
+	/// there aren't enough answers left, players are notified and the
+	/// exhaustion policy applies.  Returns `false` if the game ended.
+	fn deal_cards(&mut self, ids: &[usize]) -> bool {
+		let mut ids = ids.to_vec();
+		let missing = |players: &BTreeMap<usize, Player>, ids: &[usize]| {
+			ids.iter().map(|id| N_CARDS_IN_HAND.saturating_sub(players[id].hand.len())).sum::<usize>()
+		};
+
+		if missing(&self.players, &ids) > self.answers.n_undrawn() {
+			// Hands can't be taken back while players are choosing from them
+			let policy = match self.rules.exhaustion_policy {
+				ExhaustionPolicy::RecycleHands if self.round.is_some() => ExhaustionPolicy::DealFewer,
+				policy => policy,
+			};
+			warn!(missing = missing(&self.players, &ids), left = self.answers.n_undrawn(), ?policy, "not enough answers left");
+			self.cards_exhausted(policy);
+			match policy {
+				ExhaustionPolicy::EndGame => {
+					self.end_game();
+					return false;
+				},
+				ExhaustionPolicy::RecycleHands => {
+					let Game { players, answers, .. } = self;
+					for player in players.values_mut() {
+						discard(answers, &player.hand);
+						player.hand.clear();
+					}
+					ids = players.keys().copied().collect();
+				},
+				ExhaustionPolicy::DealFewer => {},
+			}
+		}
+
+		let Game { players, answers, .. } = self;
+		if missing(players, &ids) <= answers.n_undrawn() {
+			for id in &ids {
+				let hand = &mut players.get_mut(id).unwrap().hand;
+				let n_cards = N_CARDS_IN_HAND.saturating_sub(hand.len());
+				match answers.draw(n_cards) {
+					Ok(cards) => hand.extend(cards),
+					Err(e) => error!(error = %e, "couldn't deal cards"),
+				}
+			}
+		} else {
+			// Deal one card at a time, to share the cards that are left
+			let mut dealt = true;
+			while dealt {
+				dealt = false;
+				for id in &ids {
+					let hand = &mut players.get_mut(id).unwrap().hand;
+					if hand.len() < N_CARDS_IN_HAND {
+						if let Ok(card) = answers.draw_once() {
+							hand.push(card);
+							dealt = true;
+						}
+					}
+				}
+			}
+		}
+		true
+	}
+
+	/// Whether all players who have yet to answer `prompt` have enough cards
+	/// in their hand to, as some hands aren't full when answers run out.
+	fn can_answer(&self, czar: usize, prompt: &Prompt) -> bool {
+		let answered = |id: &usize| match &self.round {
+			Some(round) => round.state == RoundState::Judging || round.answers.contains_key(id),
+			None => false,
+		};
+		self.players.iter()
+			.filter(|(id, _)| **id != czar && !answered(id))
+			.all(|(_, player)| player.hand.len() >= prompt.n_answers as usize)
+	}
+
+	/// Tells players that the cards ran out, and what the game does about it
+	fn cards_exhausted(&mut self, policy: ExhaustionPolicy) {
+		self.effects.record(Event::CardsExhausted { policy });
+		self.broadcast_to_players(&WsMsg::CardsExhausted(policy));
+	}
+
+	fn new_round(&mut self) -> Result<()> {
+		let mut next_czar = 0;
+		
+		// Discard current round
+		if let Some(Round{ prompt, answers, czar, .. }) = self.round.take() {
+			next_czar = czar+1;
+			discard(&mut self.prompts, &[prompt]);
+			for cards in answers.values() {
+				discard(&mut self.answers, cards);
+			}
+		}
+
+		self.start_round(next_czar)
+	}
+
+	/// Cancels the current round without scoring it: the prompt is discarded,
+	/// and submitted answers are returned to their owners.
+	fn cancel_round(&mut self) -> Option<Round> {
+		let mut round = self.round.take()?;
+		self.effects.record(Event::RoundCancelled);
+		discard(&mut self.prompts, &[round.prompt.clone()]);
+		for (id, player) in self.players.iter_mut() {
+			let cards = round.answers.remove(id).into_iter().flatten();
+			player.hand.extend(cards.map(|drawn| Drawn { card: drawn.card.unfilled(), ..drawn }));
+		}
+		Some(round)
+	}
+
+	/// Starts a round with the given player as Czar, or the next one if they
+	/// aren't playing.
+	fn start_round(&mut self, mut next_czar: usize) -> Result<()> {
+		if self.players.is_empty() {
+			bail!("There are no players!");
+		}
+
+		// Find next czar
+		let mut player_ids = self.players.keys().collect::<Vec<_>>();
+		player_ids.sort_unstable();
+		if let Err(idx) = player_ids.binary_search(&&next_czar) {
+			// There's no player with ID next_czar
+			if idx == player_ids.len() {
+				// There isn't a greater key
+				next_czar = *player_ids[0];
+			} else {
+				// There is a key greater than next_czar
+				next_czar = *player_ids[idx];
+			}
+		}
+
+		let prompt = match self.prompts.draw_once() {
+			Ok(prompt) => prompt,
+			Err(e) => {
+				warn!(error = %e, "no prompts left");
+				self.cards_exhausted(ExhaustionPolicy::EndGame);
+				self.end_game();
+				return Ok(());
+			},
+		};
+		if !self.deal_cards(&self.players.keys().copied().collect::<Vec<_>>()) {
+			return Ok(());
+		}
+		if !self.can_answer(next_czar, &prompt.card) {
+			// Even sharing the answers left, someone couldn't play
+			warn!(prompt = %prompt.card, "not enough answers left to answer the prompt");
+			self.cards_exhausted(ExhaustionPolicy::EndGame);
+			self.end_game();
+			return Ok(());
+		}
+
+		// Create new round
+		debug!(players = ?self.players.keys().collect::<Vec<_>>(), "choosing next Czar");
+		let round = Round {
+			prompt,
+			// TODO cycle Czars
+			czar: next_czar,
+			answers: Default::default(),
+			submissions: Default::default(),
+			state: RoundState::Answering,
+			reactions: Default::default(),
+		};
+
+		info!(czar = round.czar, prompt = %round.prompt.card, "new round");
+		self.effects.record(Event::RoundStarted { czar: round.czar, prompt: round.prompt.card.clone() });
+
+		// Notify players
+		for (id, player) in &mut self.players {
+			let role = if *id == round.czar { Role::Czar } else { Role::Player };
+			self.effects.send(*id, WsMsg::NewRound {
+				role,
+				prompt: round.prompt.card.clone(),
+				hand: deck::cards(&player.hand),
+			});
+		}
+
+		// Set new round
+		self.round = Some(round);
+
+		Ok(())
+	}
+
+	fn broadcast_to_players(&mut self, msg: &WsMsg) {
+		for id in self.players.keys() {
+			self.effects.send(*id, msg.clone());
+		}
+	}
+
+	fn host_name(&self) -> Option<String> {
+		self.host.map(|id| self.players[&id].name.clone())
+	}
+
+	/// Removes a player from the game, returning their cards to the deck.  If
+	/// they were Czar, the round is restarted, if there aren't enough players
+	/// left, the game ends, and if everyone else answered, judging starts.
+	fn remove_player(&mut self, user_id: usize) -> Option<Player> {
+		for votes in self.save_votes.values_mut() {
+			votes.remove(&user_id);
+		}
+		self.kick_votes.remove(&user_id);
+		for votes in self.kick_votes.values_mut() {
+			votes.remove(&user_id);
+		}
+
+		let removed = self.players.remove(&user_id);
+
+		if let Some(player) = &removed {
+			self.effects.record(Event::PlayerRemoved { user: user_id });
+
+			// Discard player's answers
+			discard(&mut self.answers, &player.hand);
+
+			// Discard player's submitted answers, if any, and reactions to them
+			let mut user_is_czar = false;
+			if let Game {
+				answers,
+				round: Some(Round { answers: submitted_answers, submissions, reactions, czar, .. }),
+				..
+			} = self {
+				if let Some(cards) = submitted_answers.remove(&user_id) {
+					discard(answers, &cards);
+				}
+				submissions.retain(|_, author| *author != user_id);
+				reactions.retain(&submissions.keys().copied().collect::<Vec<_>>());
+				user_is_czar = *czar == user_id;
+			}
+
+			// If player is Czar, return submitted answers to owners and restart round
+			if user_is_czar {
+				let round = self.cancel_round().unwrap();
+				if !self.players.is_empty() {
+					self.start_round(round.czar + 1).expect("Couldn't start new round");
+				}
+			}
+
+			if self.players.is_empty() {
+				self.history.clear();
+			}
+
+			if self.host == Some(user_id) {
+				self.host = None;
+				self.broadcast_to_players(&WsMsg::HostChanged { name: None });
+
+				// Nobody else can resume the game
+				if self.paused {
+					self.paused = false;
+					self.effects.record(Event::Resumed);
+					self.broadcast_to_players(&WsMsg::Resumed);
+				}
+			}
+		}
+
+		// If not enough players, cancel round
+		if self.players.len() < self.rules.min_players {
+			self.end_game();
+		}
+
+		// The player may have been the last one to answer
+		self.start_judging();
+
+		removed
+	}
+
+	/// Starts judging, if all players but the Czar have answered.
+	fn start_judging(&mut self) {
+		let Game { players, round, effects, rng, rules, .. } = self;
+		let round = match round {
+			Some(round) if round.state == RoundState::Answering && players.contains_key(&round.czar) => round,
+			_ => return,
+		};
+		if round.answers.is_empty() || round.answers.len() + 1 < players.len() {
+			return;
+		}
+
+		round.state = RoundState::Judging;
+		effects.record(Event::JudgingStarted);
+
+		// Submissions are judged anonymously
+		let mut authors = round.answers.keys().copied().collect::<Vec<_>>();
+		authors.sort_unstable();
+		let mut ids = (0..authors.len()).collect::<Vec<_>>();
+		ids.shuffle(rng);
+		round.submissions = ids.into_iter().zip(authors).collect();
+
+		// Notify players that JUDGEMENT HAS BEGUN
+		for id in players.keys() {
+			effects.send(*id, WsMsg::ReadyToJudge {
+				answers: round.submissions.iter().map(|(id, author)| (*id, deck::cards(&round.answers[author]))).collect(),
+				points: rules.points_per_rank.clone(),
+			});
+		}
+	}
+
+	/// Ends the game: the round is dropped, and all cards are returned to the
+	/// decks.  A new game starts when the next player joins.
+	fn end_game(&mut self) {
+		if self.round.take().is_some() {
+			self.effects.record(Event::GameEnded);
+		}
+		self.paused = false;
+		self.answers.reset();
+		self.prompts.reset();
+
+		for id in self.players.keys() {
+			self.effects.send(*id, WsMsg::GameEnded);
+		}
+
+		// Clear player hands, to avoid double-discard
+		for player in self.players.values_mut() {
+			player.hand.clear();
+		}
+	}
+
+	/// Removes a player from the game and bans them temporarily.
+	fn kick_player(&mut self, user_id: usize) {
+		if let Some(player) = self.remove_player(user_id) {
+			info!(player = user_id, name = %player.name, "kicking player");
+			let ban_end = self.now + KICK_BAN_DURATION;
+			self.banned_names.insert(player.name.clone(), ban_end);
+			self.banned_clients.insert(user_id, ban_end);
+
+			if self.clients.contains(&user_id) {
+				self.effects.send(user_id, WsMsg::Kicked);
+			}
+			self.broadcast_to_players(&WsMsg::PlayerKicked { name: player.name });
+		}
+	}
+
+	fn is_banned(&mut self, user_id: usize, username: &str) -> bool {
+		let now = self.now;
+		self.banned_names.retain(|_, ban_end| *ban_end > now);
+		self.banned_clients.retain(|_, ban_end| *ban_end > now);
+		self.banned_names.contains_key(username) || self.banned_clients.contains_key(&user_id)
+	}
+
+	fn find_player(&self, name: &str) -> Option<usize> {
+		self.players.iter().find(|(_, player)| player.name == name).map(|(id, _)| *id)
+	}
+}
+
+/// Returns cards to their deck.  If they can't be, the game has lost track
+/// of them: this is logged, rather than bringing down the game.
+fn discard<Card: Clone + Eq>(deck: &mut Deck<Card>, cards: &[Drawn<Card>]) {
+	if let Err(e) = deck.discard_all(cards) {
+		error!(error = %e, "couldn't discard cards");
+	}
+}
+
+/// Takes the submitted cards out of `hand`, filling in the text of blank
+/// cards.  If a card isn't in the hand or the text of a blank card isn't
+/// valid, `hand` is left untouched and `None` is returned.
+fn play_cards(hand: &mut Vec<Drawn<Answer>>, submitted: Vec<Answer>) -> Option<Vec<Drawn<Answer>>> {
+	let mut remaining = hand.clone();
+	let mut played = Vec::with_capacity(submitted.len());
+
+	for answer in submitted {
+		let i = remaining.iter().position(|drawn| drawn.card == answer.unfilled())?;
+		let drawn = remaining.remove(i);
+		if answer.blank {
+			let content = sanitize_text(&answer.content, MAX_WRITTEN_ANSWER_LENGTH)?;
+			played.push(Drawn { card: Answer { content, blank: true }, ..drawn });
+		} else {
+			played.push(drawn);
+		}
+	}
+
+	*hand = remaining;
+	Some(played)
+}
+
+#[derive(PartialEq)]
+enum RoundState {
+	Answering,
+	Judging,
+}
+
+struct Round {
+	prompt: Drawn<Prompt>,
+	czar: usize,
+	/// Answers submitted by each player
+	answers: HashMap<usize, Vec<Drawn<Answer>>>,
+	/// Author of each submission, by the ID it's judged under.  IDs are
+	/// shuffled once all players have answered, and differ from round to round.
+	submissions: HashMap<usize, usize>,
+	state: RoundState,
+	reactions: Reactions,
+}
+
+struct Player {
+	name: String,
+	hand: Vec<Drawn<Answer>>,
+	score: u64,
+	chat_limiter: RateLimiter,
+}
+
+/// Log an invalid query from a client, and report it as an effect
+macro_rules! reject {
+	($effects:expr, $query:expr, $reason:literal $(, $($field:tt)+)?) => {{
+		let query: &'static str = $query;
+		warn!(query, $($($field)+,)? concat!("invalid query: ", $reason));
+		$effects.reject(query, $reason);
+	}};
+}
+
+/// Name of a query from a client, to label logs and metrics
+pub fn query_name(msg: &WsMsg) -> &'static str {
+	match msg {
+		WsMsg::Login(_) => "Login",
+		WsMsg::SubmitAnswer(_) => "SubmitAnswer",
+		WsMsg::SubmitJudgement(_) => "SubmitJudgement",
+		WsMsg::KickPlayer(_) => "KickPlayer",
+		WsMsg::Pause => "Pause",
+		WsMsg::Resume => "Resume",
+		WsMsg::SkipPrompt => "SkipPrompt",
+		WsMsg::VoteKick(_) => "VoteKick",
+		WsMsg::ChatSend(_) => "ChatSend",
+		WsMsg::ReactToSubmission { .. } => "ReactToSubmission",
+		WsMsg::ReactToWinner { .. } => "ReactToWinner",
+		WsMsg::GetRoundHistory => "GetRoundHistory",
+		WsMsg::ListPacks => "ListPacks",
+		WsMsg::SelectPacks(_) => "SelectPacks",
+		WsMsg::VoteSaveAnswer(_) => "VoteSaveAnswer",
+		_ => "Other",
+	}
+}
+
+/// Handles a message from a client
+fn process_message(game: &mut Game, user_id: PlayerId, msg: WsMsg) -> Result<()> {
+	match msg {
+		WsMsg::Login(username) => {
+			if game.players.contains_key(&user_id) {
+				reject!(game.effects, "Login", "user is already logged in");
+				return Ok(())
+			}
+			if game.is_banned(user_id, &username) {
+				reject!(game.effects, "Login", "user is banned");
+				game.effects.send(user_id, WsMsg::LoginRejected(LoginRejectedReason::Banned));
+				return Ok(())
+			}
+			if game.players.len() >= game.rules.max_players {
+				reject!(game.effects, "Login", "game is full");
+				game.effects.send(user_id, WsMsg::LoginRejected(LoginRejectedReason::GameIsFull));
+				return Ok(())
+			}
+			if game.players.values().any(|player| player.name == username) {
+				reject!(game.effects, "Login", "username is taken");
+				game.effects.send(user_id, WsMsg::LoginRejected(LoginRejectedReason::UsernameIsTaken));
+				return Ok(())
+			}
+			game.effects.send(user_id, WsMsg::LoginAccepted);
+
+			let player = Player {
+				name: username.clone(),
+				hand: vec![],
+				score: 0,
+				chat_limiter: RateLimiter::new(CHAT_RATE_LIMIT, CHAT_RATE_WINDOW),
+			};
+
+			game.effects.record(Event::PlayerJoined { user: user_id, name: username.clone() });
+			game.players.insert(user_id, player);
+			if !game.deal_cards(&[user_id]) {
+				info!("the game ended for lack of answers, and starts again once there are enough players");
+			}
+
+			// Notify other players
+			game.broadcast_to_players(&WsMsg::PlayerJoined { name: username });
+
+			// First player becomes host
+			if FIRST_PLAYER_IS_HOST && game.players.len() == 1 {
+				game.host = Some(user_id);
+			}
+
+			game.effects.send(user_id, WsMsg::PlayerList(game.players.values().map(|player| player.name.clone()).collect()));
+			game.effects.send(user_id, WsMsg::HostChanged { name: game.host_name() });
+			if game.paused {
+				game.effects.send(user_id, WsMsg::Paused);
+			}
+			game.effects.send(user_id, WsMsg::ChatHistory(game.chat_history.iter().cloned().collect()));
+
+			// Only start new round if there are enough players
+			if game.players.len() >= game.rules.min_players {
+				if game.round.is_none() {
+					// TODO lobby
+					info!("enough players joined, starting game");
+					game.new_round()?;
+				} else if let Some(round) = game.round.as_ref().filter(|round| !game.can_answer(round.czar, &round.prompt.card)) {
+					// Hands can't be recycled while others are choosing from
+					// them, so the new player may be left without enough cards
+					info!("new player can't answer the prompt, starting a new round");
+					let czar = round.czar;
+					game.cancel_round();
+					game.start_round(czar)?;
+				}
+
+				// If in judgement, or if the game ended for lack of cards, don't
+				// send NewRound
+				if let Some(round) = game.round.as_ref().filter(|round| round.state == RoundState::Answering) {
+					let role = if round.czar == user_id { Role::Czar } else { Role::Player };
+					game.effects.send(user_id, WsMsg::NewRound {
+						role,
+						prompt: round.prompt.card.clone(),
+						hand: deck::cards(&game.players[&user_id].hand),
+					});
+				}
+			}
+
+			Ok(())
+		},
+
+		// WsMsg::Register(name) => todo!(),
+		// WsMsg::Ready => todo!(),
+		// WsMsg::NotReady => todo!(),
+
+		WsMsg::SubmitAnswer(answers) => {
+			if let Game {
+				players,
+				round: Some(round),
+				paused,
+				effects,
+				..
+			} = &mut *game {
+				if round.state != RoundState::Answering {
+					reject!(effects, "SubmitAnswer", "round is in judgement phase");
+					return Ok(())
+				}
+
+				if *paused {
+					reject!(effects, "SubmitAnswer", "game is paused");
+					effects.send(user_id, WsMsg::AnswerRejected);
+					return Ok(())
+				}
+
+				if round.czar == user_id {
+					reject!(effects, "SubmitAnswer", "player is Czar");
+					return Ok(())
+				}
+
+				match round.answers.entry(user_id) {
+					hash_map::Entry::Occupied(_) => {
+						reject!(effects, "SubmitAnswer", "player already submitted answer")
+					},
+					hash_map::Entry::Vacant(entry) => {
+						let hand = &mut players.get_mut(&user_id).unwrap().hand;
+						// Remove cards from player's hand
+						let answers = match play_cards(hand, answers) {
+							Some(answers) => answers,
+							None => {
+								reject!(effects, "SubmitAnswer", "cards are not in player's deck or are badly written");
+								effects.send(user_id, WsMsg::AnswerRejected);
+								return Ok(())
+							}
+						};
+						debug!(answers = %answers.iter().map(|drawn| drawn.card.to_string()).collect::<Vec<_>>().join(", "), "answer accepted");
+						// Insert cards into submitted answers
+						effects.record(Event::AnswerAccepted { user: user_id, answers: deck::cards(&answers) });
+						entry.insert(answers);
+						effects.send(user_id, WsMsg::AnswerAccepted);
+					},
+				}
+
+			} else {
+				reject!(game.effects, "SubmitAnswer", "there is no ongoing round");
+			}
+
+			game.start_judging();
+			// TODO send AnswerAccepted/Rejected messages
+			Ok(())
+		},
+
+		WsMsg::SubmitJudgement(ranking) => {
+			let mut new_round = false;
+
+			if let Game {
+				players,
+				round: Some(round),
+				save_votes,
+				paused,
+				winner_reactions,
+				winners,
+				history,
+				effects,
+				rules,
+				..
+			} = &mut *game {
+				if round.state != RoundState::Judging {
+					reject!(effects, "SubmitJudgement", "round isn't in judgement phase");
+					return Ok(())
+				}
+
+				if *paused {
+					reject!(effects, "SubmitJudgement", "game is paused");
+					effects.send(user_id, WsMsg::JudgementRejected);
+					return Ok(())
+				}
+
+				if round.czar != user_id {
+					reject!(effects, "SubmitJudgement", "player isn't Czar");
+					return Ok(())
+				}
+
+				// Submissions of players who left can't be ranked
+				let is_submitted = |id: &usize| match round.submissions.get(id) {
+					Some(author) => players.contains_key(author) && round.answers.contains_key(author),
+					None => false,
+				};
+
+				match score_ranking(&ranking, &rules.points_per_rank) {
+					Some(ranked) if !ranked.iter().all(|(id, ..)| is_submitted(id)) => {
+						reject!(effects, "SubmitJudgement", "submission ID does not exist");
+						effects.send(user_id, WsMsg::JudgementRejected);
+					},
+					None => {
+						reject!(effects, "SubmitJudgement", "invalid ranking");
+						effects.send(user_id, WsMsg::JudgementRejected);
+					},
+					Some(ranked) => {
+						effects.record(Event::RoundEnded { ranking: ranked.clone() });
+						// Increment ranked players' scores
+						let ranking = ranked.into_iter().filter_map(|(id, rank, points)| {
+							let author = round.submissions.get(&id)?;
+							let player = players.get_mut(author)?;
+							player.score += points;
+							Some(RankedAnswers {
+								rank,
+								submission: id,
+								player: player.name.clone(),
+								answers: deck::cards(round.answers.get(author)?),
+								points,
+							})
+						}).collect::<Vec<_>>();
+						let crowd_favourite = round.reactions.favourite()
+							.and_then(|id| players.get(round.submissions.get(&id)?))
+							.map(|player| player.name.clone());
+						let winner = ranking.first().map(|ranked| round.prompt.card.fill(&ranked.answers));
+						info!(winner = ?winner, crowd_favourite = ?crowd_favourite, "round ended");
+						let scores = players.values().map(|player| (player.name.clone(), player.score)).collect();
+						history.push(RoundRecord {
+							prompt: round.prompt.card.clone(),
+							czar: players.get(&round.czar).map(|czar| czar.name.clone()).unwrap_or_default(),
+							submissions: round.answers.iter().filter_map(|(id, answers)| {
+								Some(SubmissionRecord {
+									player: players.get(id)?.name.clone(),
+									answers: deck::cards(answers),
+								})
+							}).collect(),
+							ranking: ranking.clone(),
+							crowd_favourite: crowd_favourite.clone(),
+						});
+						let written_answers = round.answers
+							.values()
+							.flatten()
+							.filter(|drawn| drawn.card.blank)
+							.map(|drawn| drawn.card.clone())
+							.collect::<Vec<_>>();
+						*save_votes = written_answers.iter().map(|answer| (answer.clone(), HashSet::new())).collect();
+
+						// Keep reactions to the winners, players can keep reacting to them
+						let winner_ids = ranking.iter()
+							.filter(|ranked| ranked.rank == 0)
+							.map(|ranked| ranked.submission)
+							.collect::<Vec<_>>();
+						*winner_reactions = mem::take(&mut round.reactions);
+						winner_reactions.retain(&winner_ids);
+						for &winner in &winner_ids {
+							winner_reactions.insert(winner);
+						}
+						*winners = winner_ids.into_iter().filter_map(|id| Some((id, *round.submissions.get(&id)?))).collect();
+
+						let msg = WsMsg::RoundEnded {
+							ranking,
+							scores,
+							written_answers,
+							crowd_favourite,
+						};
+
+						// Notify end of round, provide ranking and scores
+						for id in players.keys() {
+							effects.send(*id, msg.clone());
+						}
+
+						new_round = true;
+					}
+				}
+			} else {
+				reject!(game.effects, "SubmitJudgement", "there is no ongoing round");
+			}
+
+			if new_round {
+				game.new_round()?;
+			}
+
+			// TODO send JudgementAccepted/Rejected messages
+			Ok(())
+		},
+
+		WsMsg::KickPlayer(name) => {
+
+			if game.host != Some(user_id) {
+				reject!(game.effects, "KickPlayer", "player isn't host");
+				return Ok(())
+			}
+
+			match game.find_player(&name) {
+				Some(id) if id != user_id => game.kick_player(id),
+				_ => reject!(game.effects, "KickPlayer", "no other player has that name", name = %name),
+			}
+
+			Ok(())
+		},
+
+		WsMsg::Pause | WsMsg::Resume => {
+			let pause = matches!(msg, WsMsg::Pause);
+
+			if game.host != Some(user_id) {
+				reject!(game.effects, query_name(&msg), "player isn't host");
+				return Ok(())
+			}
+
+			if game.round.is_none() || game.paused == pause {
+				reject!(game.effects, query_name(&msg), "no ongoing round, or already done");
+				return Ok(())
+			}
+
+			game.paused = pause;
+			game.effects.record(if pause { Event::Paused } else { Event::Resumed });
+			game.broadcast_to_players(if pause { &WsMsg::Paused } else { &WsMsg::Resumed });
+
+			Ok(())
+		},
+
+		WsMsg::SkipPrompt => {
+
+			if game.host != Some(user_id) {
+				reject!(game.effects, "SkipPrompt", "player isn't host");
+				return Ok(())
+			}
+
+			// Start a new round with the same Czar
+			match game.cancel_round() {
+				Some(round) => {
+					info!(prompt = %round.prompt.card, "skipping prompt");
+					game.start_round(round.czar)?;
+				},
+				None => reject!(game.effects, "SkipPrompt", "there is no ongoing round"),
+			}
+
+			Ok(())
+		},
+
+		WsMsg::VoteKick(name) => {
+
+			if !game.players.contains_key(&user_id) {
+				reject!(game.effects, "VoteKick", "user isn't a player");
+				return Ok(())
+			}
+
+			if game.host.is_some() {
+				reject!(game.effects, "VoteKick", "only the host can kick players");
+				return Ok(())
+			}
+
+			let target = match game.find_player(&name) {
+				Some(id) if id != user_id => id,
+				_ => {
+					reject!(game.effects, "VoteKick", "no other player has that name", name = %name);
+					return Ok(())
+				}
+			};
+
+			let votes = game.kick_votes.entry(target).or_default();
+			votes.insert(user_id);
+			info!(target, votes = votes.len(), "vote to kick");
+
+			// Kick the player once a majority of the other players voted for it
+			if votes.len() * 2 > game.players.len() - 1 {
+				game.kick_player(target);
+			}
+
+			Ok(())
+		},
+
+		WsMsg::ChatSend(content) => {
+
+			let player = match game.players.get_mut(&user_id) {
+				Some(player) => player,
+				None => {
+					reject!(game.effects, "ChatSend", "user isn't a player");
+					return Ok(())
+				}
+			};
+
+			if !player.chat_limiter.allow(game.now) {
+				reject!(game.effects, "ChatSend", "player is sending messages too fast");
+				game.effects.send(user_id, WsMsg::ChatRejected);
+				return Ok(())
+			}
+
+			let message = match sanitize_text(&content, MAX_CHAT_MESSAGE_LENGTH) {
+				Some(content) => ChatMessage { from: player.name.clone(), content },
+				None => {
+					reject!(game.effects, "ChatSend", "message is empty or too long");
+					game.effects.send(user_id, WsMsg::ChatRejected);
+					return Ok(())
+				}
+			};
+
+			if game.chat_history.len() == CHAT_HISTORY_LENGTH {
+				game.chat_history.pop_front();
+			}
+			game.chat_history.push_back(message.clone());
+			game.broadcast_to_players(&WsMsg::ChatMessage(message));
+
+			Ok(())
+		},
+
+		WsMsg::ReactToSubmission { submission, reaction } => {
+
+			let round = match &mut game.round {
+				Some(round) if round.state == RoundState::Judging && round.submissions.contains_key(&submission) => round,
+				_ => {
+					reject!(game.effects, "ReactToSubmission", "no such submission being judged");
+					return Ok(())
+				}
+			};
+
+			if !game.players.contains_key(&user_id) || round.submissions[&submission] == user_id {
+				reject!(game.effects, "ReactToSubmission", "user isn't a player, or reacted to their own submission");
+				return Ok(())
+			}
+
+			let counts = round.reactions.toggle(submission, reaction, user_id);
+			game.broadcast_to_players(&WsMsg::SubmissionReactions { submission, counts });
+
+			Ok(())
+		},
+
+		WsMsg::ReactToWinner { submission, reaction } => {
+
+			if !game.players.contains_key(&user_id) || game.winners.get(&submission) == Some(&user_id) {
+				reject!(game.effects, "ReactToWinner", "user isn't a player, or reacted to their own submission");
+				return Ok(())
+			}
+
+			if !game.winner_reactions.contains(submission) {
+				reject!(game.effects, "ReactToWinner", "submission didn't win last round");
+				return Ok(())
+			}
+
+			let counts = game.winner_reactions.toggle(submission, reaction, user_id);
+			game.broadcast_to_players(&WsMsg::WinnerReactions { submission, counts });
+
+			Ok(())
+		},
+
+		WsMsg::GetRoundHistory => {
+			game.effects.send(user_id, WsMsg::RoundHistory(game.history.clone()));
+			Ok(())
+		},
+
+		WsMsg::ListPacks => {
+			game.effects.send(user_id, game.pack_list());
+			Ok(())
+		},
+
+		WsMsg::SelectPacks(ids) => {
+
+			if game.host != Some(user_id) {
+				reject!(game.effects, "SelectPacks", "player isn't host");
+				return Ok(())
+			}
+
+			if game.round.is_some() {
+				reject!(game.effects, "SelectPacks", "game is in progress");
+				return Ok(())
+			}
+
+			if ids.iter().any(|id| !game.packs.iter().any(|pack| pack.info.id == *id)) {
+				reject!(game.effects, "SelectPacks", "no such pack");
+				game.effects.send(user_id, game.pack_list());
+				return Ok(())
+			}
+
+			// Keep the packs sorted and without duplicates
+			let selected = game.packs.iter()
+				.map(|pack| pack.info.id.clone())
+				.filter(|id| ids.contains(id))
+				.collect::<Vec<_>>();
+
+			if game.select_packs(selected).is_err() {
+				reject!(game.effects, "SelectPacks", "packs have no prompts or no answers");
+				game.effects.send(user_id, game.pack_list());
+				return Ok(())
+			}
+
+			// Nobody has seen their hand before the game starts, so deal new
+			// ones from the selected packs
+			let Game { players, answers, .. } = &mut *game;
+			for player in players.values_mut() {
+				discard(answers, &player.hand);
+				player.hand.clear();
+			}
+			game.deal_cards(&game.players.keys().copied().collect::<Vec<_>>());
+			game.broadcast_to_players(&WsMsg::PacksSelected(game.selected_packs.clone()));
+
+			Ok(())
+		},
+
+		WsMsg::VoteSaveAnswer(answer) => {
+
+			if !game.players.contains_key(&user_id) {
+				reject!(game.effects, "VoteSaveAnswer", "user isn't a player");
+				return Ok(())
+			}
+
+			let n_votes = match game.save_votes.get_mut(&answer) {
+				Some(votes) => {
+					votes.insert(user_id);
+					votes.len()
+				},
+				None => {
+					reject!(game.effects, "VoteSaveAnswer", "answer wasn't written last round");
+					return Ok(())
+				}
+			};
+
+			// Save the card once a majority of players voted for it
+			if n_votes * 2 > game.players.len() {
+				game.save_votes.remove(&answer);
+				let card = Answer::new(answer.content);
+				if !game.answers.contains(&card) {
+					info!(answer = %card, "saving written answer to the deck");
+					game.effects.record(Event::AnswerSaved { answer: card.clone() });
+					game.answers.add(card.clone());
+					game.saved_answers.push(card.clone());
+					game.broadcast_to_players(&WsMsg::AnswerSaved(card));
+				}
+			}
+
+			Ok(())
+		},
+
+		_ => {
+			reject!(game.effects, "Other", "not a query");
+			Ok(())
+		},
+	}
+}
+
+fn user_disconnected(game: &mut Game, user_id: PlayerId) {
+	game.clients.remove(&user_id);
+
+	if let Some(player) = game.remove_player(user_id) {
+		// Notify other players
+		game.broadcast_to_players(&WsMsg::PlayerLeft { name: player.name });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use schema::{ContentRating, PackInfo};
+
+	fn pack(id: &str, n_prompts: usize, n_answers: usize) -> Pack {
+		let prompts = (0..n_prompts).map(|i| Prompt::from_template(format!("Prompt {} of {} is _.", i, id))).collect::<Vec<_>>();
+		let answers = (0..n_answers).map(|i| Answer::new(format!("Answer {} of {}", i, id))).collect::<Vec<_>>();
+		Pack {
+			info: PackInfo {
+				id: id.to_owned(),
+				name: id.to_owned(),
+				description: String::new(),
+				language: "en".to_owned(),
+				rating: ContentRating::Family,
+				n_prompts: prompts.len(),
+				n_answers: answers.len(),
+			},
+			prompts,
+			answers,
+		}
+	}
+
+	fn new_game() -> Game {
+		Game::new(42, vec![pack("test", 10, 30)])
+	}
+
+	fn join(game: &mut Game, user_id: PlayerId, name: &str) -> Vec<Effect> {
+		join_at(game, Duration::default(), user_id, name)
+	}
+
+	fn join_at(game: &mut Game, now: Duration, user_id: PlayerId, name: &str) -> Vec<Effect> {
+		game.apply(now, user_id, Action::Connect);
+		game.apply(now, user_id, Action::Message(WsMsg::Login(name.to_owned())))
+	}
+
+	fn sent_to(effects: &[Effect], user_id: PlayerId) -> Vec<&WsMsg> {
+		effects.iter().filter_map(|effect| match effect {
+			Effect::Send { to, message } if *to == user_id => Some(message),
+			_ => None,
+		}).collect()
+	}
+
+	#[test]
+	fn test_round_starts_with_enough_players() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+		let effects = join(&mut game, 3, "Carol");
+
+		assert!(effects.iter().any(|effect| matches!(effect, Effect::Log(Event::RoundStarted { czar: 1, .. }))));
+		for id in 1..=3 {
+			let role = sent_to(&effects, id).into_iter().find_map(|msg| match msg {
+				WsMsg::NewRound { role, hand, .. } => {
+					assert_eq!(hand.len(), N_CARDS_IN_HAND);
+					Some(role.clone())
+				},
+				_ => None,
+			});
+			assert_eq!(role, Some(if id == 1 { Role::Czar } else { Role::Player }));
+		}
+		game.check_invariants().unwrap();
+	}
+
+	/// Hand dealt to each player by the last `NewRound` they were sent
+	fn hands(effects: &[Effect]) -> HashMap<PlayerId, Vec<Answer>> {
+		effects.iter().filter_map(|effect| match effect {
+			Effect::Send { to, message: WsMsg::NewRound { hand, .. } } => Some((*to, hand.clone())),
+			_ => None,
+		}).collect()
+	}
+
+	/// Submits the first card of a player's hand
+	fn submit(game: &mut Game, user_id: PlayerId, hand: &[Answer]) -> Vec<Effect> {
+		game.apply(Duration::default(), user_id, Action::Message(WsMsg::SubmitAnswer(vec![hand[0].clone()])))
+	}
+
+	#[test]
+	fn test_points_per_rank_from_rules() {
+		let rules = Rules { points_per_rank: vec![5], ..Rules::default() };
+		let mut game = Game::with_rules(42, vec![pack("test", 10, 30)], rules);
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+		let hands = hands(&join(&mut game, 3, "Carol"));
+
+		submit(&mut game, 2, &hands[&2]);
+		let effects = submit(&mut game, 3, &hands[&3]);
+		assert!(sent_to(&effects, 1).iter().any(|msg| matches!(msg, WsMsg::ReadyToJudge { points, .. } if *points == vec![5])));
+
+		// Only the winner can be ranked
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(vec![vec![0], vec![1]])));
+		assert!(effects.contains(&Effect::Rejected { query: "SubmitJudgement", reason: "invalid ranking" }));
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(vec![vec![0]])));
+		let scores = sent_to(&effects, 1).into_iter().find_map(|msg| match msg {
+			WsMsg::RoundEnded { scores, .. } => Some(scores.clone()),
+			_ => None,
+		}).unwrap();
+		assert_eq!(scores.values().sum::<u64>(), 5);
+	}
+
+	/// Starts a round with four players, the first one being Czar and host,
+	/// where all but the last player answered.  Returns the players' hands.
+	fn all_but_one_answered() -> (Game, HashMap<PlayerId, Vec<Answer>>) {
+		let rules = Rules { min_players: 3, max_players: 8, ..Rules::default() };
+		let mut game = Game::with_rules(42, vec![pack("test", 10, 30)], rules);
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+		let mut hands = hands(&join(&mut game, 3, "Carol"));
+		hands.extend(self::hands(&join(&mut game, 4, "Dave")));
+
+		for id in 2..=3 {
+			let effects = submit(&mut game, id, &hands[&id]);
+			assert!(sent_to(&effects, 1).iter().all(|msg| !matches!(msg, WsMsg::ReadyToJudge { .. })));
+		}
+		(game, hands)
+	}
+
+	#[test]
+	fn test_judging_starts_when_last_to_answer_disconnects() {
+		let (mut game, _) = all_but_one_answered();
+		let effects = game.apply(Duration::default(), 4, Action::Disconnect);
+		assert!(sent_to(&effects, 1).iter().any(|msg| matches!(msg, WsMsg::ReadyToJudge { answers, .. } if answers.len() == 2)));
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_judging_starts_when_last_to_answer_is_kicked() {
+		let (mut game, _) = all_but_one_answered();
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::KickPlayer("Dave".to_owned())));
+		assert!(sent_to(&effects, 1).iter().any(|msg| matches!(msg, WsMsg::ReadyToJudge { answers, .. } if answers.len() == 2)));
+		game.check_invariants().unwrap();
+
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(vec![vec![0], vec![1]])));
+		assert!(sent_to(&effects, 1).iter().any(|msg| matches!(msg, WsMsg::RoundEnded { .. })));
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_submission_of_player_who_left_cant_be_ranked() {
+		let (mut game, hands) = all_but_one_answered();
+		submit(&mut game, 4, &hands[&4]);
+		game.apply(Duration::default(), 2, Action::Message(WsMsg::ReactToSubmission { submission: 0, reaction: schema::Reaction::Laugh }));
+		game.apply(Duration::default(), 4, Action::Disconnect);
+		game.check_invariants().unwrap();
+
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(vec![vec![0], vec![1], vec![2]])));
+		assert!(effects.contains(&Effect::Rejected { query: "SubmitJudgement", reason: "submission ID does not exist" }));
+		assert_eq!(sent_to(&effects, 1), vec![&WsMsg::JudgementRejected]);
+
+		let mut left = game.round.as_ref().unwrap().submissions.keys().map(|id| vec![*id]).collect::<Vec<_>>();
+		left.sort_unstable();
+		assert_eq!(left.len(), 2);
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(left)));
+		assert!(sent_to(&effects, 1).iter().any(|msg| matches!(msg, WsMsg::RoundEnded { .. })));
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_login_twice_rejected() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::Login("Bob".to_owned())));
+
+		assert!(effects.contains(&Effect::Rejected { query: "Login", reason: "user is already logged in" }));
+		assert_eq!(game.n_players(), 1);
+		assert_eq!(game.details("test").players[0].name, "Alice");
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_submissions_are_anonymous() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+		let hands = hands(&join(&mut game, 3, "Carol"));
+
+		game.apply(Duration::default(), 2, Action::Message(WsMsg::SubmitAnswer(vec![hands[&2][0].clone()])));
+		let effects = game.apply(Duration::default(), 3, Action::Message(WsMsg::SubmitAnswer(vec![hands[&3][0].clone()])));
+		let submissions = sent_to(&effects, 1).into_iter().find_map(|msg| match msg {
+			WsMsg::ReadyToJudge { answers, .. } => Some(answers.clone()),
+			_ => None,
+		}).unwrap();
+		let mut ids = submissions.keys().copied().collect::<Vec<_>>();
+		ids.sort_unstable();
+		assert_eq!(ids, vec![0, 1]);
+
+		// Authors are only revealed by name, once the round ended
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SubmitJudgement(vec![vec![0], vec![1]])));
+		let ranking = sent_to(&effects, 1).into_iter().find_map(|msg| match msg {
+			WsMsg::RoundEnded { ranking, .. } => Some(ranking.clone()),
+			_ => None,
+		}).unwrap();
+		for ranked in &ranking {
+			let author = if ranked.player == "Bob" { 2 } else { 3 };
+			assert_eq!(submissions[&ranked.submission], vec![hands[&author][0].clone()]);
+		}
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_login_rejected() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		let effects = join(&mut game, 2, "Alice");
+
+		assert_eq!(sent_to(&effects, 2), vec![&WsMsg::LoginRejected(LoginRejectedReason::UsernameIsTaken)]);
+		assert!(effects.contains(&Effect::Rejected { query: "Login", reason: "username is taken" }));
+		assert_eq!(game.n_players(), 1);
+	}
+
+	#[test]
+	fn test_select_packs_rejected_keeps_hands() {
+		let mut game = Game::new(42, vec![pack("answers", 0, 30), pack("prompts", 10, 0), pack("test", 10, 30)]);
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+
+		let effects = game.apply(Duration::default(), 1, Action::Message(WsMsg::SelectPacks(vec!["prompts".to_owned()])));
+		assert!(effects.contains(&Effect::Rejected { query: "SelectPacks", reason: "packs have no prompts or no answers" }));
+		for player in game.details("test").players {
+			assert_eq!(player.hand.len(), N_CARDS_IN_HAND);
+		}
+		game.check_invariants().unwrap();
+
+		// Once accepted, hands are dealt from the selected packs
+		game.apply(Duration::default(), 1, Action::Message(WsMsg::SelectPacks(vec!["answers".to_owned(), "prompts".to_owned()])));
+		for player in game.details("test").players {
+			assert_eq!(player.hand.len(), N_CARDS_IN_HAND);
+			assert!(player.hand.iter().all(|answer| answer.content.ends_with("of answers")));
+		}
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_round_not_started_if_hands_cant_answer() {
+		let mut small = pack("small", 0, 5);
+		small.prompts.push(Prompt::from_template("_ and _.".to_owned()));
+		let mut game = Game::new(42, vec![small]);
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+
+		// The five answers are shared between three players, and the third
+		// one is left with a single card for a prompt that takes two
+		let effects = join(&mut game, 3, "Carol");
+		assert!(effects.contains(&Effect::Log(Event::CardsExhausted { policy: ExhaustionPolicy::EndGame })));
+		assert!(!sent_to(&effects, 3).iter().any(|msg| matches!(msg, WsMsg::NewRound { .. })));
+		assert!(game.details("test").round.is_none());
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_exhaustion_policy_from_rules() {
+		let rules = Rules { exhaustion_policy: ExhaustionPolicy::EndGame, ..Rules::default() };
+		let mut game = Game::with_rules(42, vec![pack("small", 10, 5)], rules);
+		join(&mut game, 1, "Alice");
+		let effects = join(&mut game, 2, "Bob");
+		assert!(effects.contains(&Effect::Log(Event::CardsExhausted { policy: ExhaustionPolicy::EndGame })));
+		assert!(game.details("test").players.iter().all(|player| player.hand.is_empty()));
+		game.check_invariants().unwrap();
+	}
+
+	#[test]
+	fn test_same_actions_same_effects() {
+		let (mut a, mut b) = (new_game(), new_game());
+		for (id, name) in [(1, "Alice"), (2, "Bob"), (3, "Carol")].iter() {
+			assert_eq!(join(&mut a, *id, name), join(&mut b, *id, name));
+		}
+		let now = Duration::from_secs(1);
+		assert_eq!(a.apply(now, 2, Action::Disconnect), b.apply(now, 2, Action::Disconnect));
+	}
+
+	#[test]
+	fn test_ban_expires_with_time_of_actions() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		join(&mut game, 2, "Bob");
+		let kicked_at = Duration::from_secs(60);
+		game.apply(kicked_at, 1, Action::Message(WsMsg::KickPlayer("Bob".to_owned())));
+
+		let effects = join_at(&mut game, kicked_at + KICK_BAN_DURATION - Duration::from_secs(1), 3, "Bob");
+		assert_eq!(sent_to(&effects, 3), vec![&WsMsg::LoginRejected(LoginRejectedReason::Banned)]);
+		let effects = join_at(&mut game, kicked_at + KICK_BAN_DURATION, 4, "Bob");
+		assert_eq!(sent_to(&effects, 4)[0], &WsMsg::LoginAccepted);
+	}
+
+	#[test]
+	fn test_rejected_admin_action_isnt_logged() {
+		let mut game = new_game();
+		join(&mut game, 1, "Alice");
+		assert!(matches!(game.apply_admin(Duration::default(), AdminAction::NewRound), Err(AdminError::NotEnoughPlayers)));
+		assert!(game.apply(Duration::default(), 1, Action::Disconnect).iter().all(|effect| !matches!(effect, Effect::Log(Event::Admin { .. }))));
+	}
+}
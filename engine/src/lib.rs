@@ -0,0 +1,27 @@
+//! The rules of the game, as a state machine: players' actions are applied
+//! to a [`Game`], which answers with the [`Effect`]s for the caller to carry
+//! out, e.g., messages to send to players.  Games have no async or I/O, so the
+//! server, bots and tests all drive the same logic.
+
+use serde::{Serialize, Deserialize};
+
+use schema::{Answer, PackInfo, Prompt};
+
+pub mod admin;
+pub mod deck;
+pub mod event;
+mod game;
+mod rate_limit;
+mod reactions;
+mod scoring;
+mod util;
+
+pub use game::{Action, Effect, Game, PlayerId, Rules, query_name};
+
+/// A pack of cards, with its metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pack {
+	pub info: PackInfo,
+	pub prompts: Vec<Prompt>,
+	pub answers: Vec<Answer>,
+}
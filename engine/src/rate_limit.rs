@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Allows at most `max_events` events during any `window` of time.  Times
+/// are given by the caller, as durations since any fixed point.
+pub struct RateLimiter {
+	max_events: usize,
+	window: Duration,
+	events: VecDeque<Duration>,
+}
+
+impl RateLimiter {
+	pub fn new(max_events: usize, window: Duration) -> Self {
+		RateLimiter {
+			max_events,
+			window,
+			events: VecDeque::with_capacity(max_events),
+		}
+	}
+
+	/// Records an event happening at `now`, unless it exceeds the rate limit.
+	pub fn allow(&mut self, now: Duration) -> bool {
+		while let Some(&time) = self.events.front() {
+			if now.saturating_sub(time) >= self.window {
+				self.events.pop_front();
+			} else {
+				break;
+			}
+		}
+
+		if self.events.len() < self.max_events {
+			self.events.push_back(now);
+			true
+		} else {
+			false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rate_limiter_blocks_burst() {
+		let mut limiter = RateLimiter::new(2, Duration::from_secs(10));
+		let now = Duration::default();
+		assert!(limiter.allow(now));
+		assert!(limiter.allow(now));
+		assert!(!limiter.allow(now));
+	}
+
+	#[test]
+	fn test_rate_limiter_window_slides() {
+		let mut limiter = RateLimiter::new(2, Duration::from_secs(10));
+		let now = Duration::default();
+		assert!(limiter.allow(now));
+		assert!(limiter.allow(now + Duration::from_secs(5)));
+		assert!(!limiter.allow(now + Duration::from_secs(9)));
+		assert!(limiter.allow(now + Duration::from_secs(10)));
+		assert!(!limiter.allow(now + Duration::from_secs(11)));
+		assert!(limiter.allow(now + Duration::from_secs(15)));
+	}
+
+	#[test]
+	fn test_rate_limiter_rejected_events_dont_count() {
+		let mut limiter = RateLimiter::new(1, Duration::from_secs(10));
+		let now = Duration::default();
+		assert!(limiter.allow(now));
+		assert!(!limiter.allow(now + Duration::from_secs(5)));
+		assert!(limiter.allow(now + Duration::from_secs(10)));
+	}
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+
+use schema::Reaction;
+
+/// Reactions of players to submissions
+#[derive(Default)]
+pub struct Reactions {
+	submissions: HashMap<usize, HashMap<Reaction, HashSet<usize>>>,
+}
+
+impl Reactions {
+	/// Adds a player's reaction to a submission, or removes it if they already
+	/// reacted that way.  Returns the updated reaction counts of the submission.
+	pub fn toggle(&mut self, submission: usize, reaction: Reaction, player: usize) -> HashMap<Reaction, usize> {
+		let players = self.submissions
+			.entry(submission)
+			.or_default()
+			.entry(reaction)
+			.or_default();
+		if !players.remove(&player) {
+			players.insert(player);
+		}
+		self.counts(submission)
+	}
+
+	pub fn counts(&self, submission: usize) -> HashMap<Reaction, usize> {
+		self.submissions
+			.get(&submission)
+			.map(|reactions| {
+				reactions.iter()
+					.filter(|(_, players)| !players.is_empty())
+					.map(|(reaction, players)| (*reaction, players.len()))
+					.collect()
+			})
+			.unwrap_or_default()
+	}
+
+	fn total(&self, submission: usize) -> usize {
+		self.counts(submission).values().sum()
+	}
+
+	/// Returns the submission with the most reactions, unless there's a tie or
+	/// no reactions at all.
+	pub fn favourite(&self) -> Option<usize> {
+		let mut totals = self.submissions.keys()
+			.map(|&submission| (self.total(submission), submission))
+			.filter(|(total, _)| *total > 0)
+			.collect::<Vec<_>>();
+		totals.sort_unstable_by(|a, b| b.cmp(a));
+		match totals.as_slice() {
+			[(first, submission), (second, _), ..] if first > second => Some(*submission),
+			[(_, submission)] => Some(*submission),
+			_ => None,
+		}
+	}
+
+	/// Keeps only the reactions to the given submissions.
+	pub fn retain(&mut self, submissions: &[usize]) {
+		self.submissions.retain(|submission, _| submissions.contains(submission));
+	}
+
+	pub fn contains(&self, submission: usize) -> bool {
+		self.submissions.contains_key(&submission)
+	}
+
+	/// Makes sure a submission can be reacted to, even if nobody did yet.
+	pub fn insert(&mut self, submission: usize) {
+		self.submissions.entry(submission).or_default();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_reactions_toggle() {
+		let mut reactions = Reactions::default();
+		reactions.toggle(1, Reaction::Laugh, 10);
+		let counts = reactions.toggle(1, Reaction::Laugh, 11);
+		assert_eq!(counts.get(&Reaction::Laugh), Some(&2));
+		let counts = reactions.toggle(1, Reaction::Laugh, 10);
+		assert_eq!(counts.get(&Reaction::Laugh), Some(&1));
+		let counts = reactions.toggle(1, Reaction::Laugh, 11);
+		assert_eq!(counts.get(&Reaction::Laugh), None);
+	}
+
+	#[test]
+	fn test_reactions_favourite() {
+		let mut reactions = Reactions::default();
+		assert_eq!(reactions.favourite(), None);
+		reactions.toggle(1, Reaction::Laugh, 10);
+		assert_eq!(reactions.favourite(), Some(1));
+		reactions.toggle(2, Reaction::Love, 10);
+		reactions.toggle(2, Reaction::Laugh, 11);
+		assert_eq!(reactions.favourite(), Some(2));
+	}
+
+	#[test]
+	fn test_reactions_favourite_tie() {
+		let mut reactions = Reactions::default();
+		reactions.toggle(1, Reaction::Laugh, 10);
+		reactions.toggle(2, Reaction::Laugh, 11);
+		assert_eq!(reactions.favourite(), None);
+	}
+
+	#[test]
+	fn test_reactions_favourite_ignores_removed() {
+		let mut reactions = Reactions::default();
+		reactions.toggle(1, Reaction::Laugh, 10);
+		reactions.toggle(1, Reaction::Laugh, 10);
+		reactions.toggle(2, Reaction::Cringe, 11);
+		assert_eq!(reactions.favourite(), Some(2));
+	}
+
+	#[test]
+	fn test_reactions_retain() {
+		let mut reactions = Reactions::default();
+		reactions.toggle(1, Reaction::Laugh, 10);
+		reactions.toggle(2, Reaction::Laugh, 11);
+		reactions.retain(&[2]);
+		assert!(!reactions.contains(1));
+		assert_eq!(reactions.counts(2).get(&Reaction::Laugh), Some(&1));
+	}
+}
@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+/// Scores a ranking of submissions, given as groups of tied submissions from
+/// best to worst.  `points` is the number of points awarded for each position
+/// in the ranking.  Tied submissions share a position, and push the following
+/// ones down: ranking `[[a, b], [c]]` puts `a` and `b` first, and `c` third.
+///
+/// Returns the submission, position and points of each ranked submission, or
+/// `None` if the ranking is empty, contains an empty group or a duplicate, or
+/// ranks a submission at a position that isn't worth any points.
+pub fn score_ranking(ranking: &[Vec<usize>], points: &[u64]) -> Option<Vec<(usize, usize, u64)>> {
+	if ranking.is_empty() {
+		return None
+	}
+
+	let mut seen = HashSet::new();
+	let mut scores = vec![];
+	let mut position = 0;
+
+	for group in ranking {
+		if group.is_empty() || position >= points.len() {
+			return None
+		}
+		for &submission in group {
+			if !seen.insert(submission) {
+				return None
+			}
+			scores.push((submission, position, points[position]));
+		}
+		position += group.len();
+	}
+
+	Some(scores)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_score_ranking() {
+		let scores = score_ranking(&[vec![4], vec![2], vec![7]], &[3, 2, 1]);
+		assert_eq!(scores, Some(vec![(4, 0, 3), (2, 1, 2), (7, 2, 1)]));
+	}
+
+	#[test]
+	fn test_score_ranking_winner_only() {
+		assert_eq!(score_ranking(&[vec![4]], &[3, 2, 1]), Some(vec![(4, 0, 3)]));
+	}
+
+	#[test]
+	fn test_score_ranking_ties() {
+		let scores = score_ranking(&[vec![4, 2], vec![7]], &[3, 2, 1]);
+		assert_eq!(scores, Some(vec![(4, 0, 3), (2, 0, 3), (7, 2, 1)]));
+	}
+
+	#[test]
+	fn test_score_ranking_ties_push_past_points() {
+		assert_eq!(score_ranking(&[vec![4, 2, 1], vec![7]], &[3, 2, 1]), None);
+		assert_eq!(score_ranking(&[vec![4, 2, 1, 7]], &[3, 2, 1]).map(|s| s.len()), Some(4));
+	}
+
+	#[test]
+	fn test_score_ranking_invalid() {
+		assert_eq!(score_ranking(&[], &[3, 2, 1]), None);
+		assert_eq!(score_ranking(&[vec![]], &[3, 2, 1]), None);
+		assert_eq!(score_ranking(&[vec![4], vec![4]], &[3, 2, 1]), None);
+		assert_eq!(score_ranking(&[vec![4], vec![2]], &[1]), None);
+	}
+}
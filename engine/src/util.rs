@@ -0,0 +1,40 @@
+/// Cleans up text written by a player, e.g., on a blank card or in the chat:
+/// control characters are removed and whitespace is collapsed.  Returns
+/// `None` if the result is empty or longer than `max_length` characters.
+pub fn sanitize_text(src: &str, max_length: usize) -> Option<String> {
+	let content = src
+		.split_whitespace()
+		.map(|word| word.chars().filter(|c| !c.is_control()).collect::<String>())
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<_>>()
+		.join(" ");
+
+	if content.is_empty() || content.chars().count() > max_length {
+		None
+	} else {
+		Some(content)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sanitize_text() {
+		assert_eq!(sanitize_text("  Hello \t\n world ", 20), Some("Hello world".to_owned()));
+		assert_eq!(sanitize_text("Bell\u{7}s", 20), Some("Bells".to_owned()));
+	}
+
+	#[test]
+	fn test_sanitize_text_rejects_empty() {
+		assert_eq!(sanitize_text("", 20), None);
+		assert_eq!(sanitize_text(" \u{0} \n ", 20), None);
+	}
+
+	#[test]
+	fn test_sanitize_text_rejects_too_long() {
+		assert_eq!(sanitize_text("Hello world", 10), None);
+		assert_eq!(sanitize_text("Héllo wörld", 11), Some("Héllo wörld".to_owned()));
+	}
+}
@@ -2,7 +2,7 @@ use yew::prelude::*;
 
 use schema::{Prompt, Answer};
 
-use crate::cards::{view_prompt, view_answer};
+use crate::cards::{view_prompt, view_answer, view_blank_answer};
 
 pub struct AnswerSelector {
     link: ComponentLink<Self>,
@@ -20,6 +20,7 @@ pub struct Props {
 pub enum Msg {
     SelectAnswer(usize),
     UnselectAnswer(usize),
+    WriteAnswer(usize, String),
     SubmitAnswer,
 }
 
@@ -49,13 +50,19 @@ impl Component for AnswerSelector {
                     .unwrap_or(self.props.prompt.n_answers as usize - 1);
                 
                 if let Some(old_answer) = self.selected_answers[next_answer].replace(self.props.hand.remove(i)) {
-                    self.props.hand.push(old_answer);
+                    self.props.hand.push(old_answer.unfilled());
                 }
             }
 
             Msg::UnselectAnswer(i) => {
                 if let Some(old_answer) = self.selected_answers[i].take() {
-                    self.props.hand.push(old_answer);
+                    self.props.hand.push(old_answer.unfilled());
+                }
+            }
+
+            Msg::WriteAnswer(i, content) => {
+                if let Some(answer) = &mut self.selected_answers[i] {
+                    answer.content = content;
                 }
             }
 
@@ -77,7 +84,11 @@ impl Component for AnswerSelector {
     }
 
     fn view(&self) -> Html {
-        let can_submit = self.selected_answers.iter().all(Option::is_some);
+        // Blank cards must be written before they can be submitted
+        let can_submit = self.selected_answers.iter().all(|answer| match answer {
+            Some(answer) => !answer.blank || !answer.content.trim().is_empty(),
+            None => false,
+        });
         html! {
             <div>
                 <div style="display: flex; justify-content: center; flex-wrap: wrap;">
@@ -86,7 +97,15 @@ impl Component for AnswerSelector {
                         {
                             for self.selected_answers.iter().enumerate().map(|(i, answer)| {
                                 if let Some(ref answer) = answer {
-                                    view_answer(answer, Some(self.link.callback(move |_| Msg::UnselectAnswer(i))))
+                                    if answer.blank {
+                                        view_blank_answer(
+                                            answer,
+                                            self.link.callback(move |e: InputData| Msg::WriteAnswer(i, e.value)),
+                                            self.link.callback(move |_| Msg::UnselectAnswer(i)),
+                                        )
+                                    } else {
+                                        view_answer(answer, Some(self.link.callback(move |_| Msg::UnselectAnswer(i))))
+                                    }
                                 } else {
                                     html!{ <div class="card card-placeholder"></div> }
                                 }
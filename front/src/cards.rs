@@ -1,13 +1,14 @@
 use yew::prelude::*;
+use std::collections::HashMap;
 
-use schema::{Prompt, Answer};
+use schema::{Prompt, Answer, FilledPart, Reaction, BLANK};
 
 
 pub fn view_prompt(prompt: &Prompt) -> Html {
     let class = format!("card {}", "card-prompt");
     let insides = html!{
         <>
-        <div class="content">{ &prompt.content }</div>
+        <div class="content">{ prompt.text() }</div>
         {
             if prompt.n_answers > 1 {
                 html! { <div class="n_answers">{ format!("PICK {}", prompt.n_answers) }</div> }
@@ -22,10 +23,33 @@ pub fn view_prompt(prompt: &Prompt) -> Html {
     }
 }
 
+/// Prompt card with the answers written into its blanks
+pub fn view_filled_prompt(prompt: &Prompt, answers: &[Answer]) -> Html {
+    html! {
+        <div class="card card-prompt card-filled">
+            <div class="content">
+                {
+                    for prompt.fill_parts(answers).into_iter().map(|part| match part {
+                        FilledPart::Text(text) => html! { { text } },
+                        FilledPart::Answer(answer) => html! { <span class="answer">{ answer }</span> },
+                        FilledPart::Blank => html! { { BLANK } },
+                    })
+                }
+            </div>
+        </div>
+    }
+}
+
 pub fn view_answer(answer: &Answer, callback: Option<Callback<yew::MouseEvent>>) -> Html {
     let class = format!("card {}", "card-answer");
-    let insides = html!{
-        <div class="content">{ &answer.content }</div>
+    let insides = if answer.blank && answer.content.is_empty() {
+        html!{
+            <div class="content blank">{ "Blank card: write your own answer!" }</div>
+        }
+    } else {
+        html!{
+            <div class="content">{ &answer.content }</div>
+        }
     };
     if let Some(callback) = callback {
         html! {
@@ -39,3 +63,42 @@ pub fn view_answer(answer: &Answer, callback: Option<Callback<yew::MouseEvent>>)
         }
     }
 }
+
+pub fn view_blank_answer(
+    answer: &Answer,
+    oninput: Callback<InputData>,
+    onremove: Callback<yew::MouseEvent>,
+) -> Html {
+    html! {
+        <div class="card card-answer">
+            <textarea
+                class="content"
+                placeholder="Write your answer"
+                value=&answer.content
+                oninput=oninput
+            />
+            <div class="remove" style="cursor: pointer;" onclick=onremove>{ "Remove" }</div>
+        </div>
+    }
+}
+
+pub fn view_reactions(counts: Option<&HashMap<Reaction, usize>>, on_react: Callback<Reaction>) -> Html {
+    html! {
+        <div class="reactions">
+            {
+                for Reaction::ALL.iter().map(|&reaction| {
+                    let count = counts.and_then(|counts| counts.get(&reaction)).copied().unwrap_or(0);
+                    html! {
+                        <button onclick=on_react.reform(move |e: yew::MouseEvent| {
+                            e.stop_propagation();
+                            reaction
+                        })>
+                            { reaction.emoji() }
+                            { if count > 0 { format!(" {}", count) } else { String::new() } }
+                        </button>
+                    }
+                })
+            }
+        </div>
+    }
+}
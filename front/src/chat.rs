@@ -0,0 +1,165 @@
+use yew::prelude::*;
+
+use schema::{ChatMessage, Message as WsMsg};
+use crate::websocket::WebSocket;
+
+pub struct Chat {
+    link: ComponentLink<Self>,
+    ws: Box<dyn Bridge<WebSocket>>,
+    props: Props,
+    messages: Vec<ChatMessage>,
+    draft: String,
+    open: bool,
+    n_unread: usize,
+    error: bool,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    /// The chat stays mounted while logging in, so that it receives the
+    /// history sent upon joining, but it's only shown once logged in
+    pub visible: bool,
+}
+
+pub enum Msg {
+    WsMsg(WsMsg),
+    Toggle,
+    Update(String),
+    Send,
+}
+
+impl Component for Chat {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let ws = WebSocket::bridge(link.callback(|msg| Msg::WsMsg(msg)));
+        Self {
+            link,
+            ws,
+            props,
+            messages: vec![],
+            draft: String::new(),
+            open: false,
+            n_unread: 0,
+            error: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Toggle => {
+                self.open = !self.open;
+                self.n_unread = 0;
+                true
+            },
+
+            Msg::Update(value) => {
+                self.draft = value;
+                self.error = false;
+                false
+            },
+
+            Msg::Send => {
+                if !self.draft.trim().is_empty() {
+                    self.ws.send(WsMsg::ChatSend(self.draft.clone()));
+                    self.draft.clear();
+                }
+                true
+            },
+
+            Msg::WsMsg(msg) => match msg {
+                WsMsg::ChatHistory(messages) => {
+                    self.messages = messages;
+                    true
+                },
+
+                WsMsg::ChatMessage(message) => {
+                    self.messages.push(message);
+                    if !self.open {
+                        self.n_unread += 1;
+                    }
+                    true
+                },
+
+                WsMsg::ChatRejected => {
+                    self.error = true;
+                    true
+                },
+
+                WsMsg::Kicked => {
+                    self.messages.clear();
+                    self.n_unread = 0;
+                    true
+                },
+
+                _ => false,
+            },
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        if !self.props.visible {
+            return html! {};
+        }
+
+        let toggle_label = if self.open {
+            "Hide chat".to_owned()
+        } else if self.n_unread > 0 {
+            format!("Chat ({})", self.n_unread)
+        } else {
+            "Chat".to_owned()
+        };
+
+        html! {
+            <div class="chat">
+                <button class="chat-toggle" onclick=self.link.callback(|_| Msg::Toggle)>{ toggle_label }</button>
+                {
+                    if self.open {
+                        html! {
+                            <>
+                            <ul class="chat-messages">
+                                {
+                                    for self.messages.iter().map(|message| html! {
+                                        <li><b>{ &message.from }</b>{ ": " }{ &message.content }</li>
+                                    })
+                                }
+                            </ul>
+                            {
+                                if self.error {
+                                    html! { <span style="color: red">{"Message too long, or sent too fast"}</span> }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            <div style="display: flex;">
+                                <input
+                                    type="text"
+                                    placeholder="Say something"
+                                    value=&self.draft
+                                    oninput=self.link.callback(|e: InputData| Msg::Update(e.value))
+                                    onkeypress=self.link.batch_callback(|e: KeyboardEvent| {
+                                        if e.key() == "Enter" { vec![Msg::Send] } else { vec![] }
+                                    })
+                                />
+                                <button onclick=self.link.callback(|_| Msg::Send)>{"Send"}</button>
+                            </div>
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use yew::prelude::*;
+
+use schema::{Message as WsMsg, RoundRecord};
+use crate::websocket::WebSocket;
+
+pub struct History {
+    _ws: Box<dyn Bridge<WebSocket>>,
+    rounds: Option<Vec<RoundRecord>>,
+}
+
+pub enum Msg {
+    WsMsg(WsMsg),
+}
+
+impl Component for History {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut ws = WebSocket::bridge(link.callback(|msg| Msg::WsMsg(msg)));
+        ws.send(WsMsg::GetRoundHistory);
+        Self {
+            _ws: ws,
+            rounds: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::WsMsg(WsMsg::RoundHistory(rounds)) => {
+                self.rounds = Some(rounds);
+                true
+            },
+
+            Msg::WsMsg(_) => false,
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let view_round = |(i, round): (usize, &RoundRecord)| {
+            html! {
+                <div class="history-round">
+                    <h3>{ format!("Round {} (Czar: {})", i + 1, round.czar) }</h3>
+                    <ul>
+                        {
+                            for round.ranking.iter().map(|ranked| html! {
+                                <li>
+                                    <b>{ format!("#{} {}: ", ranked.rank + 1, ranked.player) }</b>
+                                    { round.prompt.fill(&ranked.answers) }
+                                </li>
+                            })
+                        }
+                        {
+                            for round.submissions.iter()
+                                .filter(|submission| !round.ranking.iter().any(|ranked| ranked.player == submission.player))
+                                .map(|submission| html! {
+                                    <li>
+                                        { format!("{}: ", submission.player) }
+                                        { round.prompt.fill(&submission.answers) }
+                                    </li>
+                                })
+                        }
+                    </ul>
+                    {
+                        if let Some(name) = &round.crowd_favourite {
+                            html! { <p>{ format!("Crowd favourite: {}", name) }</p> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </div>
+            }
+        };
+
+        match &self.rounds {
+            None => html! { <h2>{"Loading history..."}</h2> },
+            Some(rounds) if rounds.is_empty() => html! { <h2>{"No rounds were played yet"}</h2> },
+            Some(rounds) => html! {
+                <>
+                <h2>{"History"}</h2>
+                { for rounds.iter().enumerate().rev().map(view_round) }
+                </>
+            },
+        }
+    }
+}
@@ -1,27 +1,32 @@
 use yew::prelude::*;
 use std::collections::HashMap;
 
-use schema::{Prompt, Answer};
+use schema::{Prompt, Answer, Reaction};
 
-use crate::cards::{view_prompt, view_answer};
+use crate::cards::{view_filled_prompt, view_reactions};
 
 pub struct Judgement {
     link: ComponentLink<Self>,
     props: Props,
-    selection: Option<usize>,
+    /// Groups of tied submissions, from best to worst
+    selection: Vec<Vec<usize>>,
 }
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props {
     pub prompt: Prompt,
     pub answers: HashMap<usize, Vec<Answer>>,
+    /// Points awarded for each position in the ranking
+    pub points: Vec<u64>,
+    pub reactions: HashMap<usize, HashMap<Reaction, usize>>,
+    pub on_react: Callback<(usize, Reaction)>,
     #[prop_or_default]
-    pub on_judge: Option<Callback<usize>>,
+    pub on_judge: Option<Callback<Vec<Vec<usize>>>>,
 }
 
 pub enum Msg {
     Select(usize),
-    // Unselect(usize),
+    TieWithPrevious(usize),
     Submit,
 }
 
@@ -33,25 +38,42 @@ impl Component for Judgement {
         Self {
             link,
             props,
-            selection: None,
+            selection: vec![],
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Select(i) => {
-                if self.can_judge() {
-                    self.selection = Some(i);
+                if !self.can_judge() {
+                    false
+                } else if self.position(i).is_some() {
+                    // Clicking a ranked submission unranks it
+                    self.unrank(i);
+                    true
+                } else if self.n_ranked() < self.props.points.len() {
+                    self.selection.push(vec![i]);
                     true
                 } else {
                     false
                 }
             },
 
+            Msg::TieWithPrevious(i) => {
+                match self.group_of(i) {
+                    Some(group) if group > 0 => {
+                        self.unrank(i);
+                        self.selection[group - 1].push(i);
+                        true
+                    }
+                    _ => false,
+                }
+            },
+
             Msg::Submit => {
-                if let Some(i) = self.selection {
+                if !self.selection.is_empty() {
                     if let Some(on_judge) = &self.props.on_judge {
-                        on_judge.emit(i);
+                        on_judge.emit(self.selection.clone());
                     } else {
                         log!("error: trying to submit when player isn't Czar");
                     }
@@ -65,28 +87,51 @@ impl Component for Judgement {
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.props = props;
-        self.selection = None;
+        self.selection = vec![];
         true
     }
 
     fn view(&self) -> Html {
         let view_submission = |(i, answers): (&usize, &Vec<Answer>)| {
             let i = *i;
-            let is_selected = self.selection.map(|sel| sel == i).unwrap_or(false);
+            let position = self.position(i);
+            let can_tie = self.group_of(i).map(|group| group > 0).unwrap_or(false);
             html! {
                 <div
-                    class={if is_selected {"selected"} else {""}}
+                    class={if position.is_some() {"selected"} else {""}}
                     style="display: flex; justify-content: center; flex-wrap: wrap;"
                     onclick=self.link.callback(move |_| Msg::Select(i))
                 >
-                    { view_prompt(&self.props.prompt) }
-                    <div style="display: flex; justify-content: center; flex-wrap: wrap;">
-                        {
-                            for answers.iter().map(|answer| {
-                                view_answer(&answer, None)
-                            })
+                    {
+                        if let Some(position) = position {
+                            html! {
+                                <div class="rank">
+                                    { format!("#{} ({} pts)", position + 1, self.props.points[position]) }
+                                    {
+                                        if can_tie {
+                                            html! {
+                                                <button onclick=self.link.callback(move |e: MouseEvent| {
+                                                    e.stop_propagation();
+                                                    Msg::TieWithPrevious(i)
+                                                })>{"Tie with previous"}</button>
+                                            }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                </div>
+                            }
+                        } else {
+                            html! {}
                         }
-                    </div>
+                    }
+                    { view_filled_prompt(&self.props.prompt, answers) }
+                    {
+                        view_reactions(
+                            self.props.reactions.get(&i),
+                            self.props.on_react.reform(move |reaction| (i, reaction)),
+                        )
+                    }
                 </div>
             }
         };
@@ -107,7 +152,7 @@ impl Component for Judgement {
                             <button
                                 class="submit-answer"
                                 onclick=self.link.callback(|_| Msg::Submit)
-                                disabled=self.selection.is_none()
+                                disabled=self.selection.is_empty()
                             >{"Submit"}</button>
                         </div>
                     }
@@ -124,4 +169,26 @@ impl Judgement {
     fn can_judge(&self) -> bool {
         self.props.on_judge.is_some()
     }
+
+    /// Index of the group of tied submissions containing submission `i`
+    fn group_of(&self, i: usize) -> Option<usize> {
+        self.selection.iter().position(|group| group.contains(&i))
+    }
+
+    /// Position of submission `i` in the ranking; tied submissions share a position
+    fn position(&self, i: usize) -> Option<usize> {
+        let group = self.group_of(i)?;
+        Some(self.selection[..group].iter().map(Vec::len).sum())
+    }
+
+    fn n_ranked(&self) -> usize {
+        self.selection.iter().map(Vec::len).sum()
+    }
+
+    fn unrank(&mut self, i: usize) {
+        for group in &mut self.selection {
+            group.retain(|&j| j != i);
+        }
+        self.selection.retain(|group| !group.is_empty());
+    }
 }
@@ -6,9 +6,13 @@ extern crate web_sys;
 mod util;
 mod answer_selector;
 mod cards;
+mod chat;
+mod history;
 mod judgement;
 mod login;
 mod notification;
+mod packs;
+mod player_list;
 mod round;
 mod websocket;
 
@@ -20,8 +24,12 @@ use yew::format::Json;
 use yew::prelude::*;
 use yew::services::websocket::{WebSocketService, WebSocketStatus, WebSocketTask};
 
+use chat::Chat;
+use history::History;
 use login::Login;
 use notification::Notification;
+use packs::PackSelector;
+use player_list::PlayerList;
 use round::Round;
 use schema::{Answer, LoginRejectedReason, Message as WsMsg, Prompt, Role};
 use websocket::WebSocket;
@@ -31,6 +39,11 @@ struct Model {
     ws: Box<dyn Bridge<WebSocket>>,
     // hand: Vec<Answer>,
     state: State,
+    username: String,
+    players: Vec<String>,
+    host: Option<String>,
+    paused: bool,
+    show_history: bool,
 }
 
 enum State {
@@ -47,6 +60,8 @@ enum State {
 
 enum Msg {
     Login(String),
+    Kick(String),
+    ToggleHistory,
     RoundExited,
     WsSend(WsMsg),
     WsOpen,
@@ -65,6 +80,11 @@ impl Component for Model {
             link,
             ws,
             state: State::LoggingIn { error: None },
+            username: String::new(),
+            players: vec![],
+            host: None,
+            paused: false,
+            show_history: false,
         }
     }
 
@@ -107,6 +127,47 @@ impl Component for Model {
 
                 WsMsg::GameEnded => {
                     self.state = State::WaitingForNextRound;
+                    self.paused = false;
+                    true
+                }
+
+                WsMsg::Paused => {
+                    self.paused = true;
+                    true
+                }
+
+                WsMsg::Resumed => {
+                    self.paused = false;
+                    true
+                }
+
+                WsMsg::PlayerList(players) => {
+                    self.players = players;
+                    true
+                }
+
+                WsMsg::PlayerJoined { name } => {
+                    if !self.players.contains(&name) {
+                        self.players.push(name);
+                    }
+                    true
+                }
+
+                WsMsg::PlayerLeft { name } | WsMsg::PlayerKicked { name } => {
+                    self.players.retain(|player| *player != name);
+                    true
+                }
+
+                WsMsg::HostChanged { name } => {
+                    self.host = name;
+                    true
+                }
+
+                WsMsg::Kicked => {
+                    self.state = State::LoggingIn { error: None };
+                    self.players.clear();
+                    self.host = None;
+                    self.paused = false;
                     true
                 }
 
@@ -127,7 +188,22 @@ impl Component for Model {
 
             Msg::Login(username) => {
                 log!("Logging in as {}...", username);
-                self.ws.send(WsMsg::Login(username));
+                self.ws.send(WsMsg::Login(username.clone()));
+                self.username = username;
+                false
+            }
+
+            Msg::ToggleHistory => {
+                self.show_history = !self.show_history;
+                true
+            }
+
+            Msg::Kick(name) => {
+                if self.host.as_ref() == Some(&self.username) {
+                    self.ws.send(WsMsg::KickPlayer(name));
+                } else {
+                    self.ws.send(WsMsg::VoteKick(name));
+                }
                 false
             }
         }
@@ -147,6 +223,7 @@ impl Component for Model {
                         let error = match error {
                             LoginRejectedReason::UsernameIsTaken => "Username already taken".to_owned(),
                             LoginRejectedReason::GameIsFull => "Ongoing game is full".to_owned(),
+                            LoginRejectedReason::Banned => "You were kicked from this game, try again later".to_owned(),
                         };
                         html!{
                             <span style="color: red">{error}</span>
@@ -157,7 +234,10 @@ impl Component for Model {
             },
 
             State::WaitingForNextRound => html! {
+                <>
                 <h2>{"Waiting for the next round to begin..."}</h2>
+                <PackSelector is_host=self.host.as_ref() == Some(&self.username) />
+                </>
             },
 
             State::OngoingRound { role, prompt, hand } => html! {
@@ -169,10 +249,65 @@ impl Component for Model {
                 />
             },
         };
+        let is_host = self.host.as_ref() == Some(&self.username);
+        let host_controls = if let (true, State::OngoingRound { .. }) = (is_host, &self.state) {
+            let (label, msg) = if self.paused { ("Resume", WsMsg::Resume) } else { ("Pause", WsMsg::Pause) };
+            html! {
+                <div class="host-controls">
+                    <button onclick=self.link.callback(move |_| Msg::WsSend(msg.clone()))>{ label }</button>
+                    <button onclick=self.link.callback(|_| Msg::WsSend(WsMsg::SkipPrompt))>{"Skip prompt"}</button>
+                </div>
+            }
+        } else {
+            html! {}
+        };
+        let state_view = if self.paused {
+            html! {
+                <div class="paused">
+                    <h2>{"The game is paused"}</h2>
+                    { state_view }
+                </div>
+            }
+        } else {
+            state_view
+        };
+        let logged_in = !matches!(self.state, State::LoggingIn { .. });
+        let player_list = if !logged_in {
+            html! {}
+        } else {
+            html! {
+                <PlayerList
+                    username=self.username.clone()
+                    players=self.players.clone()
+                    host=self.host.clone()
+                    on_kick=self.link.callback(|name| Msg::Kick(name))
+                />
+            }
+        };
         html! {
             <>
             <Notification />
-            { state_view }
+            <Chat visible=logged_in />
+            { player_list }
+            {
+                if logged_in {
+                    html! {
+                        <div class="history-toggle">
+                            <button onclick=self.link.callback(|_| Msg::ToggleHistory)>{
+                                if self.show_history { "Back to the game" } else { "History" }
+                            }</button>
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }
+            }
+            { if self.show_history { html! { <History /> } } else { html! {} } }
+            // Keep the game mounted while viewing the history, so it doesn't lose its state
+            <div style={ if self.show_history { "display: none;" } else { "" } }>
+                { host_controls }
+                { state_view }
+            </div>
             </>
         }
     }
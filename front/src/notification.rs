@@ -2,7 +2,7 @@ use yew::prelude::*;
 use web_sys::HtmlElement;
 use yew::services::{RenderService, render::RenderTask};
 
-use schema::Message as WsMsg;
+use schema::{Message as WsMsg, ExhaustionPolicy};
 use crate::websocket::WebSocket;
 
 
@@ -67,6 +67,30 @@ impl Component for Notification {
                     true
                 },
 
+                WsMsg::PlayerKicked { name } => {
+                    self.text = Some(format!("{} was kicked from the game", name));
+                    true
+                },
+
+                WsMsg::Kicked => {
+                    self.text = Some("You were kicked from the game".to_owned());
+                    true
+                },
+
+                WsMsg::CardsExhausted(policy) => {
+                    self.text = Some(match policy {
+                        ExhaustionPolicy::EndGame => "The answers ran out, so the game ended",
+                        ExhaustionPolicy::RecycleHands => "The answers ran out, so hands were shuffled back into the deck",
+                        ExhaustionPolicy::DealFewer => "The answers ran out, so some hands aren't full",
+                    }.to_owned());
+                    true
+                },
+
+                WsMsg::AnswerSaved(answer) => {
+                    self.text = Some(format!("{} was added to the deck", answer));
+                    true
+                },
+
                 _ => false,
             }
         }
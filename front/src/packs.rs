@@ -0,0 +1,109 @@
+use yew::prelude::*;
+
+use schema::{Message as WsMsg, PackInfo};
+use crate::websocket::WebSocket;
+
+/// Packs of cards the game is played with, which the host can choose before
+/// the game starts
+pub struct PackSelector {
+    link: ComponentLink<Self>,
+    ws: Box<dyn Bridge<WebSocket>>,
+    props: Props,
+    packs: Vec<PackInfo>,
+    selected: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    pub is_host: bool,
+}
+
+pub enum Msg {
+    Toggle(String),
+    WsMsg(WsMsg),
+}
+
+impl Component for PackSelector {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut ws = WebSocket::bridge(link.callback(|msg| Msg::WsMsg(msg)));
+        ws.send(WsMsg::ListPacks);
+        Self {
+            link,
+            ws,
+            props,
+            packs: vec![],
+            selected: vec![],
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Toggle(id) => {
+                let mut selected = self.selected.clone();
+                if selected.contains(&id) {
+                    selected.retain(|pack| *pack != id);
+                } else {
+                    selected.push(id);
+                }
+                self.ws.send(WsMsg::SelectPacks(selected));
+                false
+            }
+
+            Msg::WsMsg(WsMsg::PackList { packs, selected }) => {
+                self.packs = packs;
+                self.selected = selected;
+                true
+            }
+
+            Msg::WsMsg(WsMsg::PacksSelected(selected)) => {
+                self.selected = selected;
+                true
+            }
+
+            Msg::WsMsg(_) => false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        let view_pack = |pack: &PackInfo| {
+            let id = pack.id.clone();
+            html! {
+                <li>
+                    <label>
+                        <input
+                            type="checkbox"
+                            checked=self.selected.contains(&pack.id)
+                            disabled=!self.props.is_host
+                            onclick=self.link.callback(move |_| Msg::Toggle(id.clone()))
+                        />
+                        <b>{ &pack.name }</b>
+                        { format!(" ({}, {}) ", pack.language, pack.rating) }
+                        { format!("{} prompts, {} answers", pack.n_prompts, pack.n_answers) }
+                    </label>
+                    <p>{ &pack.description }</p>
+                </li>
+            }
+        };
+
+        html! {
+            <div class="packs">
+                <h3>{ if self.props.is_host { "Choose the packs of cards" } else { "Packs of cards" } }</h3>
+                <ul>
+                    { for self.packs.iter().map(view_pack) }
+                </ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use yew::prelude::*;
+
+pub struct PlayerList {
+    link: ComponentLink<Self>,
+    props: Props,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    pub username: String,
+    pub players: Vec<String>,
+    pub host: Option<String>,
+    /// Called with the name of a player to kick (if host) or to vote against
+    /// (if there's no host)
+    pub on_kick: Callback<String>,
+}
+
+pub enum Msg {
+    Kick(String),
+}
+
+impl Component for PlayerList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self { link, props }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Kick(name) => {
+                self.props.on_kick.emit(name);
+            }
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        let is_host = self.props.host.as_ref() == Some(&self.props.username);
+        let kick_label = if is_host {
+            Some("Kick")
+        } else if self.props.host.is_none() {
+            Some("Vote to kick")
+        } else {
+            None
+        };
+
+        let view_player = |name: &String| {
+            let is_me = *name == self.props.username;
+            let kick = if let (Some(label), false) = (kick_label, is_me) {
+                let name = name.clone();
+                html! {
+                    <button onclick=self.link.callback(move |_| Msg::Kick(name.clone()))>{ label }</button>
+                }
+            } else {
+                html! {}
+            };
+            html! {
+                <li>
+                    { name }
+                    { if self.props.host.as_ref() == Some(name) { " (host)" } else { "" } }
+                    { kick }
+                </li>
+            }
+        };
+
+        html! {
+            <ul class="players">
+                { for self.props.players.iter().map(view_player) }
+            </ul>
+        }
+    }
+}
@@ -10,17 +10,21 @@ use std::rc::Rc;
 use std::convert::TryFrom;
 use std::collections::HashMap;
 
-use schema::{Message as WsMsg, Role, Prompt, Answer};
+use schema::{Message as WsMsg, Role, Prompt, Answer, RankedAnswers, Reaction};
 use crate::answer_selector::AnswerSelector;
 use crate::judgement::Judgement;
 use crate::websocket::WebSocket;
-use crate::cards::{view_prompt, view_answer};
+use crate::cards::{view_prompt, view_filled_prompt, view_answer, view_reactions};
 
 struct RoundResults {
     prompt: Prompt,
-    winner: String,
-    winning_answers: Vec<Answer>,
+    ranking: Vec<RankedAnswers>,
     scores: HashMap<String, u64>,
+    written_answers: Vec<Answer>,
+    /// Written answers we voted to save
+    save_votes: Vec<Answer>,
+    crowd_favourite: Option<String>,
+    winner_reactions: HashMap<usize, HashMap<Reaction, usize>>,
 }
 
 pub struct Round {
@@ -29,6 +33,8 @@ pub struct Round {
     state: State,
     ws: Box<dyn Bridge<WebSocket>>,
     results: Option<RoundResults>,
+    /// Reactions to the submissions being judged
+    reactions: HashMap<usize, HashMap<Reaction, usize>>,
 }
 
 #[derive(Clone, PartialEq, Properties)]
@@ -46,16 +52,19 @@ enum State {
     SelectingAnswers,
     WaitingForAnswersApproval(Vec<Answer>),
     WaitingForOtherPlayers(Vec<Answer>),
-    AwaitingJudgement(HashMap<usize, Vec<Answer>>),
+    AwaitingJudgement(HashMap<usize, Vec<Answer>>, Vec<u64>),
     // Czar states
     WaitingForAnswers,
-    JudgingAnswers(HashMap<usize, Vec<Answer>>),
+    JudgingAnswers(HashMap<usize, Vec<Answer>>, Vec<u64>),
     // Common states
 }
 
 pub enum Msg {
     SubmitAnswer(Vec<Answer>),
-    SubmitJudgement(usize),
+    SubmitJudgement(Vec<Vec<usize>>),
+    VoteSaveAnswer(Answer),
+    ReactToSubmission(usize, Reaction),
+    ReactToWinner(usize, Reaction),
     RoundExited,
     WsMsg(WsMsg),
 }
@@ -96,6 +105,7 @@ impl Component for Round {
             ws,
             results: None,
             // results: Some(results),
+            reactions: HashMap::new(),
         }
     }
 
@@ -110,15 +120,35 @@ impl Component for Round {
                 false
             },
 
-            Msg::SubmitJudgement(id) => {
+            Msg::SubmitJudgement(ranking) => {
                 // Submit to server
-                self.ws.send(WsMsg::SubmitJudgement(id));
+                self.ws.send(WsMsg::SubmitJudgement(ranking));
                 // Switch state
                 // self.state = State::WaitingForJudgementApproval(answers);
 
                 false
             },
 
+            Msg::VoteSaveAnswer(answer) => {
+                if let Some(results) = &mut self.results {
+                    self.ws.send(WsMsg::VoteSaveAnswer(answer.clone()));
+                    results.save_votes.push(answer);
+                    true
+                } else {
+                    false
+                }
+            },
+
+            Msg::ReactToSubmission(submission, reaction) => {
+                self.ws.send(WsMsg::ReactToSubmission { submission, reaction });
+                false
+            },
+
+            Msg::ReactToWinner(submission, reaction) => {
+                self.ws.send(WsMsg::ReactToWinner { submission, reaction });
+                false
+            },
+
             Msg::RoundExited => {
                 self.results = None;
                 self.props.on_exit.emit(());
@@ -127,7 +157,16 @@ impl Component for Round {
             }
 
             Msg::WsMsg(msg) => match msg {
-                WsMsg::AnswerRejected => todo!(),
+                WsMsg::AnswerRejected => {
+                    if let State::WaitingForAnswersApproval(_) = &self.state {
+                        // Let the player pick (or write) their answer again
+                        self.state = State::SelectingAnswers;
+                        true
+                    } else {
+                        log!("error: WsMsg::AnswerRejected: no answer was submitted");
+                        false
+                    }
+                },
 
                 WsMsg::AnswerAccepted => {
                     if let State::WaitingForAnswersApproval(answers) = &self.state {
@@ -142,26 +181,53 @@ impl Component for Round {
                     }
                 },
 
-                WsMsg::ReadyToJudge(answers) => {
+                WsMsg::ReadyToJudge { answers, points } => {
                     log!("Ready to judge");
                     match self.props.role {
                         Role::Czar => {
                             log!("I'm a Czar ready to judge");
-                            self.state = State::JudgingAnswers(answers);
+                            self.state = State::JudgingAnswers(answers, points);
                         },
                         Role::Player => {
-                            self.state = State::AwaitingJudgement(answers);
+                            self.state = State::AwaitingJudgement(answers, points);
                         }
                     }
                     true
                 },
 
-                WsMsg::RoundEnded { winner, winning_answers, scores } => {
+                WsMsg::JudgementRejected => {
+                    log!("error: WsMsg::JudgementRejected: ranking was rejected");
+                    false
+                },
+
+                WsMsg::SubmissionReactions { submission, counts } => {
+                    self.reactions.insert(submission, counts);
+                    true
+                },
+
+                WsMsg::WinnerReactions { submission, counts } => {
+                    if let Some(results) = &mut self.results {
+                        results.winner_reactions.insert(submission, counts);
+                        true
+                    } else {
+                        false
+                    }
+                },
+
+                WsMsg::RoundEnded { ranking, scores, written_answers, crowd_favourite } => {
+                    // Reactions to the winners carry over
+                    let winner_reactions = ranking.iter()
+                        .filter(|ranked| ranked.rank == 0)
+                        .filter_map(|ranked| self.reactions.get(&ranked.submission).map(|counts| (ranked.submission, counts.clone())))
+                        .collect();
                     self.results = Some(RoundResults {
                         prompt: self.props.prompt.clone(),
-                        winner,
-                        winning_answers,
+                        ranking,
                         scores,
+                        written_answers,
+                        save_votes: vec![],
+                        crowd_favourite,
+                        winner_reactions,
                     });
                     // self.state = State::DisplayingResults { winner, winning_answers, scores };
                     true
@@ -177,6 +243,7 @@ impl Component for Round {
             Role::Player => State::SelectingAnswers,
             Role::Czar => State::WaitingForAnswers,
         };
+        self.reactions.clear();
         self.props = props;
         true
     }
@@ -184,28 +251,59 @@ impl Component for Round {
     fn view(&self) -> Html {
         if let Some(RoundResults{
             prompt,
-            winner,
-            winning_answers,
+            ranking,
             scores,
+            written_answers,
+            save_votes,
+            crowd_favourite,
+            winner_reactions,
         }) = &self.results {
             let mut sorted_scores = scores
                 .iter()
                 .collect::<Vec<_>>();
             // Sort scores from bigger to smaller
             sorted_scores.sort_by(|(_, &a), (_, &b)| b.cmp(&a));
+            let winners = ranking
+                .iter()
+                .filter(|ranked| ranked.rank == 0)
+                .map(|ranked| ranked.player.as_str())
+                .collect::<Vec<_>>();
+            let title = if winners.len() == 1 {
+                format!("{} has won!", winners[0])
+            } else {
+                format!("{} are tied!", winners.join(" and "))
+            };
             html!{
                 <>
-                <h2>{ format!("{} has won!", winner) }</h2>
-                <div style="display: flex; justify-content: center; flex-wrap: wrap;">
-                    { view_prompt(&prompt) }
-                    <div style="display: flex; justify-content: center; flex-wrap: wrap;">
-                        {
-                            for winning_answers.iter().map(|answer| {
-                                view_answer(&answer, None)
-                            })
-                        }
-                    </div>
-                </div>
+                <h2>{ title }</h2>
+                {
+                    for ranking.iter().map(|ranked| html!{
+                        <>
+                        <h3>{ format!("#{} {} (+{})", ranked.rank + 1, ranked.player, ranked.points) }</h3>
+                        <div style="display: flex; justify-content: center; flex-wrap: wrap;">
+                            { view_filled_prompt(&prompt, &ranked.answers) }
+                            {
+                                if ranked.rank == 0 {
+                                    let submission = ranked.submission;
+                                    view_reactions(
+                                        winner_reactions.get(&submission),
+                                        self.link.callback(move |reaction| Msg::ReactToWinner(submission, reaction)),
+                                    )
+                                } else {
+                                    html!{}
+                                }
+                            }
+                        </div>
+                        </>
+                    })
+                }
+                {
+                    if let Some(name) = crowd_favourite {
+                        html!{ <h3>{ format!("Crowd favourite: {}", name) }</h3> }
+                    } else {
+                        html!{}
+                    }
+                }
                 <table class="scores">
                     <tr><th class="left">{"Player"}</th><th class="right">{"Score"}</th></tr>
                     {
@@ -214,6 +312,36 @@ impl Component for Round {
                         })
                     }
                 </table>
+                {
+                    if written_answers.is_empty() {
+                        html!{}
+                    } else {
+                        html!{
+                            <>
+                            <h2>{"Keep any of these cards?"}</h2>
+                            <div style="display: flex; justify-content: center; flex-wrap: wrap;">
+                                {
+                                    for written_answers.iter().map(|answer| {
+                                        let voted = save_votes.contains(answer);
+                                        let vote = answer.clone();
+                                        html!{
+                                            <div>
+                                                { view_answer(&answer, None) }
+                                                <div style="display: flex; justify-content: center;">
+                                                    <button
+                                                        onclick=self.link.callback(move |_| Msg::VoteSaveAnswer(vote.clone()))
+                                                        disabled=voted
+                                                    >{ if voted { "Voted" } else { "Save to deck" } }</button>
+                                                </div>
+                                            </div>
+                                        }
+                                    })
+                                }
+                            </div>
+                            </>
+                        }
+                    }
+                }
                 <div class="next-round">
                     <button onclick=self.link.callback(|_| Msg::RoundExited)>{"Next round"}</button>
                 </div>
@@ -251,11 +379,14 @@ impl Component for Round {
                     </>
                 },
 
-                State::AwaitingJudgement(answers) => {
+                State::AwaitingJudgement(answers, points) => {
                     html!{
                         <Judgement
                             prompt=self.props.prompt.clone()
                             answers=answers.clone()
+                            points=points.clone()
+                            reactions=self.reactions.clone()
+                            on_react=self.link.callback(|(i, reaction)| Msg::ReactToSubmission(i, reaction))
                         />
                     }
                 },
@@ -271,12 +402,15 @@ impl Component for Round {
                     </>
                 },
 
-                State::JudgingAnswers(answers) => {
+                State::JudgingAnswers(answers, points) => {
                     html!{
                         <Judgement
                             prompt=self.props.prompt.clone()
                             answers=answers.clone()
-                            on_judge=self.link.callback(|i| Msg::SubmitJudgement(i))
+                            points=points.clone()
+                            reactions=self.reactions.clone()
+                            on_react=self.link.callback(|(i, reaction)| Msg::ReactToSubmission(i, reaction))
+                            on_judge=self.link.callback(|ranking| Msg::SubmitJudgement(ranking))
                         />
                     }
                 },
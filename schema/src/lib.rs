@@ -14,27 +14,236 @@ pub enum Role {
 	Czar,
 }
 
-fn default_n_answers() -> u8 { 1 }
+/// How blanks are drawn on prompts
+pub static BLANK: &str = "_____";
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+/// A prompt, written as a template: a run of underscores or `{}` is a blank,
+/// and `\` escapes the next character, e.g. `\_` for a literal underscore.
+///
+/// If `n_answers` is omitted, it's inferred from the number of blanks.  Prompts
+/// without blanks are questions, whose answer goes at the end.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RawPrompt")]
 pub struct Prompt {
 	pub content: String,
-	#[serde(default = "default_n_answers")]
 	pub n_answers: u8,
 }
 
+#[derive(Deserialize)]
+#[serde(rename = "Prompt")]
+struct RawPrompt {
+	content: String,
+	// Not an option in the file itself, where `Some(..)` would be needed
+	#[serde(default, deserialize_with = "deserialize_some")]
+	n_answers: Option<u8>,
+}
+
+fn deserialize_some<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+	u8::deserialize(deserializer).map(Some)
+}
+
+impl From<RawPrompt> for Prompt {
+	fn from(raw: RawPrompt) -> Self {
+		match raw.n_answers {
+			Some(n_answers) => Prompt::new(raw.content, n_answers),
+			None => Prompt::from_template(raw.content),
+		}
+	}
+}
+
+/// Part of a prompt, between or at blanks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptPart {
+	Text(String),
+	Blank,
+}
+
+/// Part of a prompt filled with answers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilledPart {
+	Text(String),
+	Answer(String),
+	/// A blank without an answer
+	Blank,
+}
+
 impl Prompt {
 	pub fn new(content: String, n_answers: u8) -> Self {
 		Prompt { content, n_answers }
 	}
+
+	/// Creates a prompt with one answer per blank, or one answer if it has no
+	/// blanks.
+	pub fn from_template(content: String) -> Self {
+		let mut prompt = Prompt { content, n_answers: 1 };
+		prompt.n_answers = prompt.n_blanks().clamp(1, u8::MAX as usize) as u8;
+		prompt
+	}
+
+	/// Parses the template into text and blanks, with escapes resolved
+	pub fn parts(&self) -> Vec<PromptPart> {
+		parse_template(&self.content)
+	}
+
+	pub fn n_blanks(&self) -> usize {
+		self.parts().iter().filter(|part| **part == PromptPart::Blank).count()
+	}
+
+	/// Substitutes the answers into the blanks, in order.  Answers beyond the
+	/// blanks, e.g. of prompts without blanks, are appended to the prompt.
+	///
+	/// Answers are adapted to their place in the sentence: they are capitalized
+	/// at the start of a sentence, and lowercased in the middle of one unless
+	/// they look like names.  Their final period is dropped if the prompt goes
+	/// on, and any final punctuation if the prompt has its own.
+	pub fn fill_parts(&self, answers: &[Answer]) -> Vec<FilledPart> {
+		let parts = self.parts();
+		let mut answers = answers.iter();
+		let mut filled = vec![];
+		let mut sentence_start = true;
+		for (i, part) in parts.iter().enumerate() {
+			match part {
+				PromptPart::Text(text) => {
+					sentence_start = ends_sentence(text);
+					filled.push(FilledPart::Text(text.clone()));
+				},
+				PromptPart::Blank => match answers.next() {
+					Some(answer) => {
+						let next = match parts.get(i + 1) {
+							Some(PromptPart::Text(text)) => Some(text.as_str()),
+							Some(PromptPart::Blank) => Some(""),
+							None => None,
+						};
+						filled.push(FilledPart::Answer(fit_answer(&answer.content, sentence_start, next)));
+						sentence_start = false;
+					},
+					None => filled.push(FilledPart::Blank),
+				},
+			}
+		}
+		for answer in answers {
+			let separator = if filled.is_empty() { "" } else { " " };
+			filled.push(FilledPart::Text(separator.to_owned()));
+			filled.push(FilledPart::Answer(fit_answer(&answer.content, true, None)));
+		}
+		filled
+	}
+
+	/// Completed sentence of the prompt with the answers, as per [`Prompt::fill_parts`]
+	pub fn fill(&self, answers: &[Answer]) -> String {
+		self.fill_parts(answers).into_iter()
+			.map(|part| match part {
+				FilledPart::Text(text) | FilledPart::Answer(text) => text,
+				FilledPart::Blank => BLANK.to_owned(),
+			})
+			.collect()
+	}
+
+	/// Text of the prompt as shown on its card, with blanks drawn as [`BLANK`]
+	pub fn text(&self) -> String {
+		self.parts().into_iter()
+			.map(|part| match part {
+				PromptPart::Text(text) => text,
+				PromptPart::Blank => BLANK.to_owned(),
+			})
+			.collect()
+	}
+}
+
+fn parse_template(content: &str) -> Vec<PromptPart> {
+	let mut parts = vec![];
+	let mut text = String::new();
+	let mut chars = content.chars().peekable();
+	while let Some(c) = chars.next() {
+		let blank = match c {
+			'\\' => {
+				// A trailing backslash is kept as is
+				text.push(chars.next().unwrap_or('\\'));
+				false
+			},
+			'_' => {
+				while chars.peek() == Some(&'_') {
+					chars.next();
+				}
+				true
+			},
+			'{' if chars.peek() == Some(&'}') => {
+				chars.next();
+				true
+			},
+			c => {
+				text.push(c);
+				false
+			},
+		};
+		if blank {
+			if !text.is_empty() {
+				parts.push(PromptPart::Text(std::mem::take(&mut text)));
+			}
+			parts.push(PromptPart::Blank);
+		}
+	}
+	if !text.is_empty() {
+		parts.push(PromptPart::Text(text));
+	}
+	parts
+}
+
+/// Whether text leaves the next word at the start of a sentence
+fn ends_sentence(text: &str) -> bool {
+	let text = text.trim_end_matches(|c: char| c.is_whitespace() || c == '"' || c == '\u{201C}');
+	text.is_empty() || text.ends_with(['.', '!', '?', ':'])
+}
+
+/// Adapts an answer to its place in a prompt, followed by `next` or at the end
+fn fit_answer(answer: &str, sentence_start: bool, next: Option<&str>) -> String {
+	let mut answer = answer.trim();
+	match next {
+		Some(next) if next.starts_with(|c: char| c.is_ascii_punctuation() && c != '"' && c != '\'') => {
+			answer = answer.trim_end_matches(['.', '!', '?']);
+		},
+		// Ellipses are kept
+		Some(_) if answer.ends_with('.') && !answer.ends_with("..") => {
+			answer = &answer[..answer.len() - 1];
+		},
+		_ => {},
+	}
+
+	let mut chars = answer.chars();
+	let first = match chars.next() {
+		Some(first) => first,
+		None => return String::new(),
+	};
+	let rest = chars.as_str();
+	if sentence_start {
+		first.to_uppercase().chain(rest.chars()).collect()
+	} else if looks_like_name(answer) {
+		answer.to_owned()
+	} else {
+		first.to_lowercase().chain(rest.chars()).collect()
+	}
+}
+
+/// Whether an answer seems to start with a name, which keeps its capital: a
+/// single word, an acronym, or a capitalized word followed by another.
+fn looks_like_name(answer: &str) -> bool {
+	let mut words = answer.split_whitespace();
+	let first = words.next().unwrap_or("");
+	let acronym = first.chars().filter(|c| c.is_alphabetic()).count() > 1
+		&& first.chars().filter(|c| c.is_alphabetic()).all(char::is_uppercase);
+	match words.next() {
+		_ if acronym || first == "I" || first.starts_with("I'") => true,
+		Some(second) => second.starts_with(char::is_uppercase),
+		None => true,
+	}
 }
 
 impl fmt::Display for Prompt {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		if self.n_answers == 1 {
-			write!(f, "\"{}\"", self.content)
+			write!(f, "\"{}\"", self.text())
 		} else {
-			write!(f, "\"{}\" ({} answers)", self.content, self.n_answers)
+			write!(f, "\"{}\" ({} answers)", self.text(), self.n_answers)
 		}
 	}
 }
@@ -42,27 +251,155 @@ impl fmt::Display for Prompt {
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Answer {
 	pub content: String,
+	/// Blank cards are written in by the player when they are submitted.  In
+	/// the deck and in hands, their content is empty.
+	#[serde(default)]
+	pub blank: bool,
 }
 
 impl Answer {
 	pub fn new(content: String) -> Self {
-		Answer { content }
+		Answer { content, blank: false }
+	}
+
+	pub fn blank() -> Self {
+		Answer { content: String::new(), blank: true }
+	}
+
+	/// Returns the card as it is in the deck, i.e., without the text written
+	/// on it if it's a blank card.
+	pub fn unfilled(&self) -> Self {
+		if self.blank {
+			Answer::blank()
+		} else {
+			self.clone()
+		}
 	}
 }
 
 impl fmt::Display for Answer {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "\"{}\"", self.content)
+		if self.blank && self.content.is_empty() {
+			write!(f, "(blank)")
+		} else {
+			write!(f, "\"{}\"", self.content)
+		}
 	}
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A submission ranked by the Czar at the end of a round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankedAnswers {
+	/// Position in the ranking, starting at 0.  Tied submissions share the same
+	/// position.
+	pub rank: usize,
+	/// ID of the submission, as in `ReadyToJudge`
+	pub submission: usize,
+	pub player: String,
+	pub answers: Vec<Answer>,
+	pub points: u64,
+}
+
+/// A finished round, as kept in the game's history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundRecord {
+	pub prompt: Prompt,
+	pub czar: String,
+	pub submissions: Vec<SubmissionRecord>,
+	pub ranking: Vec<RankedAnswers>,
+	pub crowd_favourite: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+	pub player: String,
+	pub answers: Vec<Answer>,
+}
+
+/// What the game does when there aren't enough answers left to deal full hands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExhaustionPolicy {
+	/// End the game, returning all cards to the decks
+	EndGame,
+	/// Take back the hands of all players, and share the cards between them
+	RecycleHands,
+	/// Deal the cards that are left, so that some hands aren't full
+	DealFewer,
+}
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Reaction {
+	Laugh,
+	Love,
+	Shock,
+	Cringe,
+	Fire,
+}
+
+impl Reaction {
+	pub const ALL: [Reaction; 5] = [
+		Reaction::Laugh,
+		Reaction::Love,
+		Reaction::Shock,
+		Reaction::Cringe,
+		Reaction::Fire,
+	];
+
+	pub fn emoji(self) -> &'static str {
+		match self {
+			Reaction::Laugh => "\u{1F602}",
+			Reaction::Love => "\u{2764}\u{FE0F}",
+			Reaction::Shock => "\u{1F631}",
+			Reaction::Cringe => "\u{1F62C}",
+			Reaction::Fire => "\u{1F525}",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ContentRating {
+	Family,
+	Teen,
+	Mature,
+}
+
+impl fmt::Display for ContentRating {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ContentRating::Family => write!(f, "Family"),
+			ContentRating::Teen => write!(f, "Teen"),
+			ContentRating::Mature => write!(f, "Mature"),
+		}
+	}
+}
+
+/// A pack of cards that the decks can be made of
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackInfo {
+	pub id: String,
+	pub name: String,
+	pub description: String,
+	pub language: String,
+	pub rating: ContentRating,
+	pub n_prompts: usize,
+	pub n_answers: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+	pub from: String,
+	pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LoginRejectedReason {
 	UsernameIsTaken,
 	GameIsFull,
+	/// The player was recently kicked from the game
+	Banned,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Message {
 	Login(String),
 	LoginAccepted,
@@ -85,20 +422,163 @@ pub enum Message {
 	SubmitAnswer(Vec<Answer>),
 	AnswerAccepted,
 	AnswerRejected,
-	ReadyToJudge(HashMap<usize, Vec<Answer>>),
-	SubmitJudgement(usize),
+	ReadyToJudge {
+		/// Submissions by ID.  IDs are drawn anew every round, so that they
+		/// don't tell who submitted what.
+		answers: HashMap<usize, Vec<Answer>>,
+		/// Points awarded for each position in the ranking
+		points: Vec<u64>,
+	},
+	/// Groups of tied submissions, from best to worst
+	SubmitJudgement(Vec<Vec<usize>>),
 	JudgementRejected,
 	// JudgementTimeout,
 	RoundEnded {
-		winner: String,
-		winning_answers: Vec<Answer>,
+		ranking: Vec<RankedAnswers>,
 		scores: HashMap<String, u64>,
+		/// Blank cards written in this round, which players can vote to save
+		written_answers: Vec<Answer>,
+		/// Player whose submission got the most reactions, if any
+		crowd_favourite: Option<String>,
 	},
+	VoteSaveAnswer(Answer),
+	AnswerSaved(Answer),
 	GameEnded,
+	/// There weren't enough answers left to deal full hands, and the game did
+	/// as per the policy
+	CardsExhausted(ExhaustionPolicy),
 	PlayerJoined {
 		name: String,
 	},
 	PlayerLeft {
 		name: String,
 	},
+	/// Players in the game, sent upon joining
+	PlayerList(Vec<String>),
+	HostChanged {
+		name: Option<String>,
+	},
+	/// Kick a player, if host
+	KickPlayer(String),
+	/// Vote to kick a player, if there's no host
+	VoteKick(String),
+	Kicked,
+	PlayerKicked {
+		name: String,
+	},
+	/// Pause or resume the game, if host
+	Pause,
+	Resume,
+	Paused,
+	Resumed,
+	/// Replace the current prompt, if host
+	SkipPrompt,
+	ChatSend(String),
+	ChatMessage(ChatMessage),
+	/// Last messages of the chat, sent upon joining
+	ChatHistory(Vec<ChatMessage>),
+	/// The message was too long, or sent too fast
+	ChatRejected,
+	/// Toggle a reaction to a submission, while judging
+	ReactToSubmission {
+		submission: usize,
+		reaction: Reaction,
+	},
+	/// Toggle a reaction to a winning submission, after the round ended
+	ReactToWinner {
+		submission: usize,
+		reaction: Reaction,
+	},
+	SubmissionReactions {
+		submission: usize,
+		counts: HashMap<Reaction, usize>,
+	},
+	WinnerReactions {
+		submission: usize,
+		counts: HashMap<Reaction, usize>,
+	},
+	GetRoundHistory,
+	/// Rounds played in this game, from first to last
+	RoundHistory(Vec<RoundRecord>),
+	ListPacks,
+	/// Available packs, and the IDs of those the decks are made of
+	PackList {
+		packs: Vec<PackInfo>,
+		selected: Vec<String>,
+	},
+	/// Choose the packs the decks are made of, if host and between games
+	SelectPacks(Vec<String>),
+	PacksSelected(Vec<String>),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn text(text: &str) -> PromptPart {
+		PromptPart::Text(text.to_owned())
+	}
+
+	#[test]
+	fn test_prompt_parts() {
+		let prompt = Prompt::from_template("Hello ___, I'm {} years old".to_owned());
+		assert_eq!(prompt.parts(), vec![text("Hello "), PromptPart::Blank, text(", I'm "), PromptPart::Blank, text(" years old")]);
+		assert_eq!(prompt.text(), "Hello _____, I'm _____ years old");
+		assert_eq!(prompt.n_answers, 2);
+	}
+
+	#[test]
+	fn test_prompt_escapes() {
+		let prompt = Prompt::from_template(r"snake\_case is \{}, \\ _".to_owned());
+		assert_eq!(prompt.parts(), vec![text(r"snake_case is {}, \ "), PromptPart::Blank]);
+		assert_eq!(prompt.n_answers, 1);
+	}
+
+	#[test]
+	fn test_prompt_without_blanks() {
+		let prompt = Prompt::from_template("What's that smell?".to_owned());
+		assert_eq!(prompt.parts(), vec![text("What's that smell?")]);
+		assert_eq!(prompt.n_blanks(), 0);
+		assert_eq!(prompt.n_answers, 1);
+	}
+
+	fn answers(contents: &[&str]) -> Vec<Answer> {
+		contents.iter().map(|content| Answer::new((*content).to_owned())).collect()
+	}
+
+	#[test]
+	fn test_fill() {
+		let prompt = Prompt::from_template("I drink to forget _, and {}.".to_owned());
+		assert_eq!(prompt.fill(&answers(&["Being on fire.", "Barack Obama."])), "I drink to forget being on fire, and Barack Obama.");
+		assert_eq!(prompt.fill(&answers(&["NASA."])), "I drink to forget NASA, and _____.");
+	}
+
+	#[test]
+	fn test_fill_sentence_start() {
+		let prompt = Prompt::from_template("_ is why I can't sleep. _!".to_owned());
+		assert_eq!(prompt.fill(&answers(&["a bird.", "wow..."])), "A bird is why I can't sleep. Wow!");
+	}
+
+	#[test]
+	fn test_fill_at_end() {
+		let prompt = Prompt::from_template("Today's gender is: _".to_owned());
+		assert_eq!(prompt.fill(&answers(&["Coat hangers!"])), "Today's gender is: Coat hangers!");
+		let prompt = Prompt::from_template("My plan is _".to_owned());
+		assert_eq!(prompt.fill(&answers(&["Eating the rich."])), "My plan is eating the rich.");
+	}
+
+	#[test]
+	fn test_fill_without_blanks() {
+		let prompt = Prompt::new("Make a haiku.".to_owned(), 2);
+		assert_eq!(prompt.fill(&answers(&["the sun.", "A bird"])), "Make a haiku. The sun. A bird");
+		assert_eq!(prompt.fill_parts(&answers(&["Yes."]))[1..], [FilledPart::Text(" ".to_owned()), FilledPart::Answer("Yes.".to_owned())]);
+	}
+
+	#[test]
+	fn test_prompt_infers_n_answers() {
+		let prompt: Prompt = serde_json::from_str(r#"{"content": "_ + _ = _"}"#).unwrap();
+		assert_eq!(prompt.n_answers, 3);
+		let prompt: Prompt = serde_json::from_str(r#"{"content": "Make a haiku.", "n_answers": 3}"#).unwrap();
+		assert_eq!(prompt.n_answers, 3);
+	}
 }